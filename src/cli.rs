@@ -0,0 +1,321 @@
+use crate::input::{parse_window, ChangeScope, TokenizerModel, DEFAULT_CONCURRENCY};
+use crate::output::tree::TreeStyle;
+use crate::output::SplitPolicy;
+
+pub struct CliArgs {
+    pub source: Option<String>,
+    pub output_template: String,
+    pub max_output_mb: usize,
+    pub from_list: Option<String>,
+    pub preamble: Option<String>,
+    pub postamble: Option<String>,
+    pub changed_only: Option<ChangeScope>,
+    pub ignore_case: bool,
+    pub skip_empty: bool,
+    pub no_ext_filter: bool,
+    pub tokenizer: TokenizerModel,
+    pub tree_style: Option<TreeStyle>,
+    pub table_of_contents: bool,
+    pub redact_secrets: bool,
+    pub no_confirm: bool,
+    pub confirm: bool,
+    pub all_or_nothing: bool,
+    pub wrap_column: Option<usize>,
+    pub include_submodules: bool,
+    pub converters: Vec<String>,
+    pub modified_within: Option<std::time::Duration>,
+    pub split: Option<SplitPolicy>,
+    pub path_prefix: Option<String>,
+    pub concurrency: usize,
+    pub price_model: Option<String>,
+    pub max_file_tokens: Option<usize>,
+    pub post_process: Option<String>,
+    pub verbosity: u8,
+    pub log_file: Option<String>,
+    pub show_help: bool,
+    pub show_version: bool,
+}
+
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "merged_{source_name}.txt";
+pub const DEFAULT_MAX_OUTPUT_MB: usize = 20;
+
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<CliArgs, String> {
+    let mut cli = CliArgs {
+        source: None,
+        output_template: DEFAULT_OUTPUT_TEMPLATE.to_string(),
+        max_output_mb: DEFAULT_MAX_OUTPUT_MB,
+        from_list: None,
+        preamble: None,
+        postamble: None,
+        changed_only: None,
+        ignore_case: false,
+        skip_empty: false,
+        no_ext_filter: false,
+        tokenizer: TokenizerModel::O200kBase,
+        tree_style: None,
+        table_of_contents: false,
+        redact_secrets: false,
+        no_confirm: false,
+        confirm: false,
+        all_or_nothing: false,
+        wrap_column: None,
+        include_submodules: false,
+        converters: Vec::new(),
+        modified_within: None,
+        split: None,
+        path_prefix: None,
+        concurrency: DEFAULT_CONCURRENCY,
+        price_model: None,
+        max_file_tokens: None,
+        post_process: None,
+        verbosity: 0,
+        log_file: None,
+        show_help: false,
+        show_version: false,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output-template" => {
+                cli.output_template = args
+                    .next()
+                    .ok_or_else(|| "--output-template requires a value".to_string())?;
+            }
+            "--max-output-mb" => {
+                cli.max_output_mb = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| "--max-output-mb requires a number".to_string())?;
+            }
+            "--from-list" => {
+                cli.from_list = Some(
+                    args.next()
+                        .ok_or_else(|| "--from-list requires a file path".to_string())?,
+                );
+            }
+            "--preamble" | "--postamble" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?;
+                let text = read_text_arg(&value)?;
+                if arg == "--preamble" {
+                    cli.preamble = Some(text);
+                } else {
+                    cli.postamble = Some(text);
+                }
+            }
+            "--changed-commits" => {
+                let n = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| "--changed-commits requires a positive number".to_string())?;
+                cli.changed_only = Some(ChangeScope::LastCommits(n));
+            }
+            "--changed-since" => {
+                let base = args
+                    .next()
+                    .ok_or_else(|| "--changed-since requires a branch, tag or commit".to_string())?;
+                cli.changed_only = Some(ChangeScope::SinceRef(base));
+            }
+            "--ignore-case" => {
+                cli.ignore_case = true;
+            }
+            "--tokenizer" => {
+                cli.tokenizer = args
+                    .next()
+                    .and_then(|v| TokenizerModel::from_name(&v))
+                    .ok_or_else(|| "--tokenizer requires o200k_base or cl100k_base".to_string())?;
+            }
+            "--tree-style" => {
+                cli.tree_style = Some(
+                    args.next()
+                        .and_then(|v| TreeStyle::from_name(&v))
+                        .ok_or_else(|| "--tree-style requires box, ascii or indent".to_string())?,
+                );
+            }
+            "--split-parts" | "--split-tokens" | "--split-kb" => {
+                let n: usize = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("{} requires a positive number", arg))?;
+                cli.split = Some(match arg.as_str() {
+                    "--split-parts" => SplitPolicy::Parts(n),
+                    "--split-tokens" => SplitPolicy::MaxTokens(n),
+                    _ => SplitPolicy::MaxBytes(n * 1024),
+                });
+            }
+            "--path-prefix" => {
+                cli.path_prefix = Some(
+                    args.next()
+                        .ok_or_else(|| "--path-prefix requires a value".to_string())?,
+                );
+            }
+            "--concurrency" => {
+                cli.concurrency = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| "--concurrency requires a positive number".to_string())?;
+            }
+            "--max-file-tokens" => {
+                cli.max_file_tokens = Some(
+                    args.next()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| "--max-file-tokens requires a positive number".to_string())?,
+                );
+            }
+            "--price-model" => {
+                cli.price_model = Some(
+                    args.next()
+                        .ok_or_else(|| "--price-model requires a model name".to_string())?,
+                );
+            }
+            "--post-process" => {
+                cli.post_process = Some(
+                    args.next()
+                        .ok_or_else(|| "--post-process requires a command".to_string())?,
+                );
+            }
+            "--modified-within" => {
+                cli.modified_within = Some(
+                    args.next()
+                        .as_deref()
+                        .and_then(parse_window)
+                        .ok_or_else(|| "--modified-within requires a duration such as 30m, 6h, 2d or 1w".to_string())?,
+                );
+            }
+            "--convert" => {
+                cli.converters.push(
+                    args.next()
+                        .ok_or_else(|| "--convert requires EXT=COMMAND".to_string())?,
+                );
+            }
+            "--submodules" => {
+                cli.include_submodules = true;
+            }
+            "--wrap" => {
+                cli.wrap_column = Some(
+                    args.next()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|n| *n >= 20)
+                        .ok_or_else(|| "--wrap requires a column of at least 20".to_string())?,
+                );
+            }
+            "--all-or-nothing" => {
+                cli.all_or_nothing = true;
+            }
+            "--confirm" => {
+                cli.confirm = true;
+            }
+            "--no-confirm" => {
+                cli.no_confirm = true;
+            }
+            "--redact" => {
+                cli.redact_secrets = true;
+            }
+            "--toc" => {
+                cli.table_of_contents = true;
+            }
+            "--no-ext-filter" => {
+                cli.no_ext_filter = true;
+            }
+            "-v" | "--verbose" => {
+                cli.verbosity = cli.verbosity.saturating_add(1);
+            }
+            "-vv" => {
+                cli.verbosity = cli.verbosity.saturating_add(2);
+            }
+            "--log-file" => {
+                cli.log_file = Some(
+                    args.next()
+                        .ok_or_else(|| "--log-file requires a file path".to_string())?,
+                );
+            }
+            "--skip-empty" => {
+                cli.skip_empty = true;
+            }
+            "-h" | "--help" => {
+                cli.show_help = true;
+            }
+            "-V" | "--version" => {
+                cli.show_version = true;
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option: {}", arg));
+            }
+            _ => {
+                if cli.source.is_some() {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                cli.source = Some(arg);
+            }
+        }
+    }
+    Ok(cli)
+}
+
+pub fn version() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+pub fn usage() -> String {
+    format!(
+        "{}
+Merge text files from a local directory or GitHub repository for AI chats.
+
+Usage: {} [OPTIONS] [SOURCE]
+
+Arguments:
+  [SOURCE]  Directory path, GitHub or Bitbucket URL, or - for stdin (defaults to the current directory)
+
+Options:
+      --output-template <TEMPLATE>  Default output file name; supports {{source_name}}, {{date}} and {{timestamp}} [default: {}]
+      --max-output-mb <N>           Ask for confirmation above this merge size, 0 disables [default: {}]
+      --from-list <FILE>            Index only the paths listed in FILE, one per line
+      --preamble <TEXT|@FILE>       Text placed before the merged files
+      --postamble <TEXT|@FILE>      Text placed after the merged files
+      --changed-commits <N>         GitHub: only files changed in the last N commits
+      --changed-since <REF>         GitHub: only files changed since a branch, tag or commit
+      --ignore-case                 Match .gitignore patterns and listed paths regardless of case
+      --tokenizer <MODEL>           Token counting model: o200k_base or cl100k_base [default: o200k_base]
+      --tree-style <STYLE>          Start merges with a file tree drawn as box, ascii or indent
+      --split-parts <N>             Split the merge into N files of similar token counts
+      --split-tokens <N>            Split the merge into files of at most N tokens each
+      --split-kb <N>                Split the merge into files of at most N KB each
+      --path-prefix <PREFIX>        Prefix merged paths with PREFIX instead of the source name
+      --no-ext-filter               Ignore the built-in binary extension list; skip files whose content looks binary
+      --concurrency <N>             Files fetched or counted at once; lower it if GitHub throttles you [default: {}]
+      --max-file-tokens <N>         Leave files above N tokens out of the merge, listing them at the top
+      --price-model <NAME>          Show the estimated input cost of the selection for a model from the pricing table
+      --toc                         Start merges with a table of contents listing each file's tokens and byte offset
+      --post-process <CMD>          Pipe each merged output through a shell command and use its stdout instead
+      --redact                      Mask API keys, passwords, private keys and other likely secrets as [REDACTED]
+      --no-confirm                  Merge right away instead of showing a summary to confirm first
+      --convert <EXT=COMMAND>       Extract text from EXT files with COMMAND ({{}} is the file path), e.g. pdf=\"pdftotext {{}} -\"
+      --modified-within <DURATION>  Local sources: only list files modified in the last 30m, 6h, 2d, 1w, ...
+      --all-or-nothing              With File + Clipboard, write nothing unless both the file and the clipboard succeed
+      --wrap <COL>                  Hard-wrap merged lines longer than COL characters, marking continuations with ↪
+      --submodules                  GitHub: also index submodules hosted on the same server, at their pinned commits
+      --confirm                     Show the merge summary again after `a` turned it off for good
+      --skip-empty                  Leave empty files out of the index and the merge
+  -v, --verbose                     Log progress; repeat (-vv) for debug detail. RUST_LOG also works
+      --log-file <PATH>             Write the log to PATH [default: ai-anvil-tui.log in the config directory]
+  -h, --help                        Print help and exit
+  -V, --version                     Print version and exit",
+        version(),
+        env!("CARGO_PKG_NAME"),
+        DEFAULT_OUTPUT_TEMPLATE,
+        DEFAULT_MAX_OUTPUT_MB,
+        DEFAULT_CONCURRENCY,
+    )
+}
+
+fn read_text_arg(value: &str) -> Result<String, String> {
+    match value.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path, e)),
+        None => Ok(value.to_string()),
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+pub fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    env_value("HOME").or_else(|| env_value("USERPROFILE")).map(PathBuf::from)
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env_value("ANVIL_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = env_value("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("ai-anvil-tui"));
+    }
+    if cfg!(windows) {
+        if let Some(dir) = env_value("APPDATA") {
+            return Some(PathBuf::from(dir).join("ai-anvil-tui"));
+        }
+    }
+    home_dir().map(|home| home.join(".config").join("ai-anvil-tui"))
+}
+
+pub fn expand_path(path: &str) -> String {
+    let path = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => match home_dir() {
+            Some(home) => format!("{}{}", home.to_string_lossy(), rest),
+            None => path.to_string(),
+        },
+        _ => path.to_string(),
+    };
+    expand_env_vars(&path)
+}
+
+fn expand_env_vars(path: &str) -> String {
+    let mut out = String::new();
+    let mut rest = path;
+    while let Some(pos) = rest.find(['$', '%']) {
+        out.push_str(&rest[..pos]);
+        let marker = &rest[pos..];
+        let (name, len) = if let Some(braced) = marker.strip_prefix("${") {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 3),
+                None => ("", 1),
+            }
+        } else if let Some(after) = marker.strip_prefix('$') {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end + 1)
+        } else if cfg!(windows) {
+            match marker[1..].find('%') {
+                Some(end) => (&marker[1..end + 1], end + 2),
+                None => ("", 1),
+            }
+        } else {
+            ("", 1)
+        };
+        match std::env::var(name).ok().filter(|_| !name.is_empty()) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&marker[..len]),
+        }
+        rest = &marker[len..];
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,309 @@
+use super::github::{build_http_client, rate_limit_reset, API_REQUESTS};
+use super::{fetch_all, join_url_path, FilterConfig, SourceFile, SourceType, TextSource, TextSourceError};
+use crate::config::env_value;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url::Url;
+
+#[derive(Debug, Clone)]
+pub struct BitbucketConfig {
+    pub host: String,
+    pub api_base: String,
+    pub user_agent: String,
+    pub timeout: Duration,
+    pub token: Option<String>,
+}
+
+impl Default for BitbucketConfig {
+    fn default() -> Self {
+        Self {
+            host: "bitbucket.org".to_string(),
+            api_base: "https://api.bitbucket.org/2.0".to_string(),
+            user_agent: "rust-text-source".to_string(),
+            timeout: Duration::from_secs(30),
+            token: None,
+        }
+    }
+}
+
+impl BitbucketConfig {
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(api_base) = env_value("ANVIL_BITBUCKET_API_URL") {
+            config.api_base = api_base.trim_end_matches('/').to_string();
+        }
+        if let Some(user_agent) = env_value("ANVIL_USER_AGENT") {
+            config.user_agent = user_agent;
+        }
+        config.token = env_value("BITBUCKET_TOKEN");
+        config
+    }
+    pub fn web_prefix(&self) -> String {
+        format!("https://{}", self.host)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct BitbucketRepository {
+    mainbranch: Option<BitbucketBranch>,
+}
+
+#[derive(serde::Deserialize)]
+struct BitbucketBranch {
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+struct BitbucketPage {
+    values: Vec<BitbucketEntry>,
+    next: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct BitbucketEntry {
+    path: String,
+    r#type: String,
+    size: Option<u64>,
+    commit: Option<BitbucketCommit>,
+}
+
+#[derive(serde::Deserialize)]
+struct BitbucketCommit {
+    hash: String,
+}
+
+#[derive(serde::Deserialize)]
+struct BitbucketErrorBody {
+    error: BitbucketErrorDetail,
+}
+
+#[derive(serde::Deserialize)]
+struct BitbucketErrorDetail {
+    message: String,
+}
+
+fn error_for_status(
+    status: reqwest::StatusCode,
+    reset: Option<u64>,
+    body: &str,
+    not_found: TextSourceError,
+) -> TextSourceError {
+    let message = serde_json::from_str::<BitbucketErrorBody>(body)
+        .map(|b| b.error.message)
+        .unwrap_or_else(|_| status.to_string());
+    match status.as_u16() {
+        404 => not_found,
+        429 => TextSourceError::RateLimitExceeded(reset),
+        401 | 403 => TextSourceError::PermissionDenied(message),
+        _ => TextSourceError::BitbucketError(message),
+    }
+}
+
+async fn get_json<T: for<'de> serde::Deserialize<'de>>(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<T, TextSourceError> {
+    API_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    let response = client.get(url).send().await?;
+    let status = response.status();
+    log::info!("Bitbucket API {} -> {}", response.url(), status);
+    let reset = rate_limit_reset(&response);
+    let text = response.text().await?;
+    if status.is_success() {
+        serde_json::from_str::<T>(&text).map_err(|e| TextSourceError::BitbucketError(e.to_string()))
+    } else {
+        Err(error_for_status(status, reset, &text, TextSourceError::RepoNotFound))
+    }
+}
+
+async fn fetch_raw(client: reqwest::Client, url: String, path: String) -> Result<Vec<u8>, TextSourceError> {
+    let started = Instant::now();
+    API_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    let response = client.get(&url).send().await?;
+    let status = response.status();
+    let reset = rate_limit_reset(&response);
+    let bytes = response.bytes().await?;
+    log::debug!("GET {} -> {} ({} bytes in {:?})", url, status, bytes.len(), started.elapsed());
+    if status.is_success() {
+        Ok(bytes.to_vec())
+    } else {
+        let body = String::from_utf8_lossy(&bytes);
+        Err(error_for_status(status, reset, &body, TextSourceError::PathNotFound(path)))
+    }
+}
+
+pub struct BitbucketSource {
+    pub workspace: String,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub subpath: Option<String>,
+    pub config: BitbucketConfig,
+    pub client: reqwest::Client,
+}
+
+impl BitbucketSource {
+    pub fn new(
+        workspace: String,
+        repo: String,
+        branch: Option<String>,
+        subpath: Option<String>,
+        config: BitbucketConfig,
+    ) -> Self {
+        let client = build_http_client(&config.user_agent, config.timeout, config.token.as_deref());
+        Self {
+            workspace,
+            repo,
+            branch,
+            subpath,
+            config,
+            client,
+        }
+    }
+    pub fn parse_bitbucket_url(
+        url: &str,
+        host: &str,
+    ) -> Result<(String, String, Option<String>, Option<String>), TextSourceError> {
+        let parsed = Url::parse(url).map_err(|_| TextSourceError::InvalidSource)?;
+        if parsed.scheme() != "https" || parsed.host_str() != Some(host) {
+            return Err(TextSourceError::InvalidSource);
+        }
+        let segments: Vec<&str> = parsed
+            .path_segments()
+            .map(|seg| seg.filter(|s| !s.is_empty()).collect())
+            .ok_or(TextSourceError::InvalidSource)?;
+        match segments.as_slice() {
+            [workspace, repo, "src", branch, rest @ ..] => Ok((
+                workspace.to_string(),
+                repo.trim_end_matches(".git").to_string(),
+                Some(branch.to_string()),
+                (!rest.is_empty()).then(|| rest.join("/")),
+            )),
+            [workspace, repo, ..] => Ok((
+                workspace.to_string(),
+                repo.trim_end_matches(".git").to_string(),
+                None,
+                None,
+            )),
+            _ => Err(TextSourceError::InvalidSource),
+        }
+    }
+    fn repo_url(&self) -> String {
+        format!("{}/repositories/{}/{}", self.config.api_base, self.workspace, self.repo)
+    }
+    async fn resolve_branch(&self) -> Result<String, TextSourceError> {
+        if let Some(branch) = &self.branch {
+            return Ok(branch.clone());
+        }
+        let repository: BitbucketRepository = get_json(&self.client, &self.repo_url()).await?;
+        repository
+            .mainbranch
+            .map(|b| b.name)
+            .ok_or_else(|| TextSourceError::BitbucketError("Repository has no main branch".to_string()))
+    }
+    fn full_path(&self, path: &str) -> String {
+        match &self.subpath {
+            Some(sp) => format!("{}/{}", sp, path),
+            None => path.to_string(),
+        }
+    }
+    fn raw_url(&self, source_file: &SourceFile) -> Result<String, TextSourceError> {
+        let SourceType::Bitbucket { commit, .. } = &source_file.source_type else {
+            return Err(TextSourceError::InvalidSource);
+        };
+        let base = format!("{}/src/{}", self.repo_url(), commit);
+        Ok(join_url_path(&base, &self.full_path(&source_file.path)))
+    }
+}
+
+#[async_trait]
+impl TextSource for BitbucketSource {
+    async fn get_file_index(
+        &self,
+        filter: &FilterConfig,
+    ) -> Result<Vec<SourceFile>, TextSourceError> {
+        let branch = self.resolve_branch().await?;
+        let listed: Option<HashSet<String>> = filter
+            .file_list
+            .as_ref()
+            .map(|list| list.iter().map(|p| filter.normalize_name(p)).collect());
+        let mut next = Some(format!(
+            "{}/src/{}/{}?max_depth=100&pagelen=100",
+            self.repo_url(),
+            branch,
+            self.subpath.as_deref().map(|sp| format!("{}/", sp)).unwrap_or_default()
+        ));
+        let mut files = Vec::new();
+        while let Some(url) = next {
+            let page: BitbucketPage = get_json(&self.client, &url).await?;
+            next = page.next;
+            for entry in page.values {
+                if entry.r#type != "commit_file" || (filter.skip_empty && entry.size == Some(0)) {
+                    continue;
+                }
+                let path = match &self.subpath {
+                    Some(sp) => match entry.path.strip_prefix(sp.as_str()) {
+                        Some(stripped) => stripped.trim_start_matches('/').to_string(),
+                        None => continue,
+                    },
+                    None => entry.path,
+                };
+                let included = match &listed {
+                    Some(listed) => listed.contains(&filter.normalize_name(&path)),
+                    None => match super::file_system::get_extension(&path) {
+                        Some(ext) => filter.is_text_extension(ext),
+                        None => filter.allows_no_extension(),
+                    },
+                };
+                if !included {
+                    continue;
+                }
+                let commit = entry.commit.map(|c| c.hash).unwrap_or_else(|| branch.clone());
+                files.push(SourceFile {
+                    path,
+                    source_type: SourceType::Bitbucket {
+                        workspace: self.workspace.clone(),
+                        repo: self.repo.clone(),
+                        commit: commit.clone(),
+                    },
+                    revision: Some(commit),
+                });
+            }
+        }
+        Ok(files)
+    }
+    fn web_url(&self, source_file: &SourceFile) -> Option<String> {
+        let SourceType::Bitbucket { workspace, repo, commit } = &source_file.source_type else {
+            return None;
+        };
+        let base = format!("{}/{}/{}/src/{}", self.config.web_prefix(), workspace, repo, commit);
+        Some(join_url_path(&base, &self.full_path(&source_file.path)))
+    }
+    async fn get_file_bytes(&self, source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError> {
+        let url = self.raw_url(source_file)?;
+        fetch_raw(self.client.clone(), url, source_file.path.clone()).await
+    }
+    async fn get_file_contents(
+        &self,
+        source_files: &[SourceFile],
+        filter: &FilterConfig,
+    ) -> Vec<Result<String, TextSourceError>> {
+        let shared_filter = Arc::new(filter.clone());
+        let jobs = source_files
+            .iter()
+            .map(|sf| {
+                let client = self.client.clone();
+                let url = self.raw_url(sf);
+                let path = sf.path.clone();
+                let filter = Arc::clone(&shared_filter);
+                async move {
+                    let bytes = fetch_raw(client, url?, path.clone()).await?;
+                    filter.decode_text_async(&path, bytes).await
+                }
+            })
+            .collect();
+        fetch_all(jobs, filter.concurrency).await
+    }
+}
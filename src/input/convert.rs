@@ -0,0 +1,236 @@
+use super::TextSourceError;
+use crate::config::env_value;
+use crate::output::transform::wait_for_output;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CONVERTERS_FILE: &str = "converters.txt";
+const DEFAULT_CONVERTER_TIMEOUT: Duration = Duration::from_secs(60);
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub fn parse_converter(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    let (ext, command) = line.split_once('=').or_else(|| line.split_once(char::is_whitespace))?;
+    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+    let command = command.trim();
+    if ext.is_empty() || command.is_empty() {
+        return None;
+    }
+    Some((ext, command.to_string()))
+}
+
+pub fn load_converters() -> HashMap<String, String> {
+    crate::config::config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(CONVERTERS_FILE)).ok())
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+                .filter_map(parse_converter)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn split_command(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\') => current.push(c),
+                            c => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                        },
+                        c => current.push(c),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    Some(words)
+}
+
+pub fn program_available(command: &str) -> bool {
+    let Some(program) = split_command(command).and_then(|words| words.into_iter().next()) else {
+        return false;
+    };
+    let program = program.as_str();
+    if program.contains(['/', '\\']) {
+        return Path::new(program).is_file();
+    }
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&paths).any(|dir| {
+        dir.join(program).is_file() || (cfg!(windows) && dir.join(format!("{}.exe", program)).is_file())
+    })
+}
+
+fn converter_timeout() -> Duration {
+    env_value("ANVIL_CONVERTER_TIMEOUT")
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_CONVERTER_TIMEOUT)
+}
+
+pub fn convert_file(command: &str, file: &Path, path: &str) -> Result<String, TextSourceError> {
+    convert_file_within(command, file, path, converter_timeout())
+}
+
+fn convert_file_within(command: &str, file: &Path, path: &str, timeout: Duration) -> Result<String, TextSourceError> {
+    let failed = |reason: String| TextSourceError::ConversionFailed(path.to_string(), reason);
+    let words = split_command(command).ok_or_else(|| failed("unbalanced quotes in command".to_string()))?;
+    let mut parts = words.iter();
+    let program = parts.next().ok_or_else(|| failed("empty command".to_string()))?;
+    let mut args: Vec<std::ffi::OsString> = Vec::new();
+    let mut placed = false;
+    for part in parts {
+        if part == "{}" {
+            args.push(file.as_os_str().to_owned());
+            placed = true;
+        } else if part.contains("{}") {
+            args.push(part.replace("{}", &file.to_string_lossy()).into());
+            placed = true;
+        } else {
+            args.push(part.into());
+        }
+    }
+    if !placed {
+        args.push(file.as_os_str().to_owned());
+    }
+    let child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(format!("could not start {}: {}", program, e)))?;
+    let stdout = wait_for_output(child, None, program, timeout).map_err(failed)?;
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+pub fn convert_bytes(command: &str, bytes: &[u8], path: &str) -> Result<String, TextSourceError> {
+    let ext = super::file_system::get_extension(path).unwrap_or_default();
+    let dir = private_temp_dir()?;
+    let temp = dir.join(format!("input.{}", ext));
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .and_then(|mut file| file.write_all(bytes));
+    let result = match written {
+        Ok(()) => convert_file(command, &temp, path),
+        Err(e) => Err(e.into()),
+    };
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn private_temp_dir() -> Result<PathBuf, TextSourceError> {
+    loop {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "ai-anvil-{}-{}-{:08x}",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos
+        ));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_command;
+
+    fn words(command: &str) -> Vec<String> {
+        split_command(command).unwrap()
+    }
+
+    #[test]
+    fn splits_plain_words() {
+        assert_eq!(words("pdftotext -layout {} -"), ["pdftotext", "-layout", "{}", "-"]);
+        assert_eq!(words("  pandoc   -t plain "), ["pandoc", "-t", "plain"]);
+    }
+
+    #[test]
+    fn keeps_quoted_arguments_together() {
+        assert_eq!(
+            words(r#""/opt/My Tools/conv" --title 'A B' "say \"hi\"" x"y z""#),
+            ["/opt/My Tools/conv", "--title", "A B", r#"say "hi""#, "xy z"]
+        );
+        assert_eq!(words("cmd ''"), ["cmd", ""]);
+    }
+
+    #[test]
+    fn keeps_windows_paths() {
+        assert_eq!(words(r"C:\tools\conv.exe {}"), [r"C:\tools\conv.exe", "{}"]);
+        assert_eq!(words(r#""C:\Program Files\conv.exe""#), [r"C:\Program Files\conv.exe"]);
+    }
+
+    #[test]
+    fn rejects_unbalanced_quotes() {
+        assert!(split_command("conv 'open").is_none());
+        assert!(split_command("conv \"open").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_a_hanging_converter() {
+        use std::path::Path;
+        use std::time::{Duration, Instant};
+        let started = Instant::now();
+        let err = super::convert_file_within("sh -c 'sleep 30' {}", Path::new("doc.pdf"), "doc.pdf", Duration::from_millis(300))
+            .unwrap_err();
+        assert!(err.to_string().contains("did not finish"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
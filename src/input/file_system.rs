@@ -1,7 +1,15 @@
-use super::{FilterConfig, SourceFile, SourceType, TextSource, TextSourceError};
+use super::glob::glob_match;
+use super::{fetch_all, looks_binary, FilterConfig, SourceFile, SourceType, TextSource, TextSourceError, BINARY_SNIFF_LEN};
 use async_trait::async_trait;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::fs;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use tokio::sync::mpsc;
+
+const CONTEXT_INCLUDE_FILE: &str = ".contextinclude";
 
 pub fn get_extension(path_str: &str) -> Option<String> {
     let p = Path::new(path_str);
@@ -10,6 +18,16 @@ pub fn get_extension(path_str: &str) -> Option<String> {
         .map(|e| e.to_lowercase())
 }
 
+fn file_revision(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?;
+    Some(format!("{}-{}", modified.as_nanos(), metadata.len()))
+}
+
 struct GitIgnoreRules {
     patterns: Vec<String>,
 }
@@ -20,9 +38,9 @@ impl GitIgnoreRules {
             patterns: Vec::new(),
         }
     }
-    fn load_from(base_path: &Path) -> Self {
+    fn load_from(base_path: &Path, file_name: &str) -> Self {
         let mut rules = Self::new();
-        let ignore_path = base_path.join(".gitignore");
+        let ignore_path = base_path.join(file_name);
         if ignore_path.exists() && ignore_path.is_file() {
             if let Ok(content) = fs::read_to_string(&ignore_path) {
                 for line in content.lines() {
@@ -36,52 +54,108 @@ impl GitIgnoreRules {
         }
         rules
     }
-    fn is_ignored(&self, rel_path: &str) -> bool {
-        for p in &self.patterns {
-            if self.match_pattern(rel_path, p) {
-                return true;
-            }
-        }
-        false
+    fn matching_pattern(&self, rel_path: &str, filter: &FilterConfig) -> Option<&str> {
+        let rel_path = filter.normalize_name(rel_path);
+        self.patterns
+            .iter()
+            .find(|p| self.match_pattern(&rel_path, &filter.normalize_name(p)))
+            .map(|p| p.as_str())
     }
     fn match_pattern(&self, rel_path: &str, pat: &str) -> bool {
-        let trimmed_pat = pat.trim_end_matches('/');
-        if pat.starts_with('/') {
-            let pat_no_slash = trimmed_pat.trim_start_matches('/');
-            if rel_path == pat_no_slash {
-                return true;
-            }
-            if rel_path.starts_with(&format!("{}/", pat_no_slash)) {
-                return true;
-            }
-            if pat_no_slash.starts_with('*') {
-                let after_star = pat_no_slash.trim_start_matches('*');
-                if rel_path.ends_with(after_star) {
-                    return true;
-                }
-            }
-        } else if pat.contains('*') {
-            let star_idx = pat.find('*').unwrap();
-            let (start, end) = pat.split_at(star_idx);
-            let after_star = &end[1..];
-            if rel_path.starts_with(start) && rel_path.ends_with(after_star) {
-                return true;
-            }
-        } else {
-            if rel_path == trimmed_pat {
-                return true;
+        let anchored = pat.starts_with('/');
+        let pat = pat.trim_start_matches('/').trim_end_matches('/');
+        if pat.is_empty() {
+            return false;
+        }
+        let components: Vec<&str> = rel_path.split('/').collect();
+        if !anchored && !pat.contains('/') {
+            return components.iter().any(|c| glob_match(pat, c));
+        }
+        (1..=components.len()).any(|n| glob_match(pat, &components[..n].join("/")))
+    }
+}
+
+fn file_looks_binary(path: &Path) -> bool {
+    let mut head = Vec::new();
+    match fs::File::open(path) {
+        Ok(file) => file.take(BINARY_SNIFF_LEN as u64).read_to_end(&mut head).is_err() || looks_binary(&head),
+        Err(_) => true,
+    }
+}
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+fn full_path(source_file: &SourceFile) -> Result<PathBuf, TextSourceError> {
+    let SourceType::FileSystem {
+        base_path,
+        relative_path,
+    } = &source_file.source_type
+    else {
+        return Err(TextSourceError::InvalidSource);
+    };
+    let full_path = base_path.join(relative_path);
+    if !full_path.exists() {
+        return Err(TextSourceError::PathNotFound(
+            full_path.to_string_lossy().to_string(),
+        ));
+    }
+    Ok(full_path)
+}
+
+fn read_file_bytes(source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError> {
+    Ok(fs::read(full_path(source_file)?)?)
+}
+
+fn read_file_text(source_file: &SourceFile, filter: &FilterConfig) -> Result<String, TextSourceError> {
+    let path = &source_file.path;
+    if let Some(command) = filter.converter_for(path) {
+        return super::convert::convert_file(command, &full_path(source_file)?, path);
+    }
+    let file = fs::File::open(full_path(source_file)?)?;
+    let size = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, file);
+    let mut bytes = Vec::with_capacity(size);
+    let mut validated = 0;
+    let mut sniffed = !filter.needs_content_check(path);
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        let len = chunk.len();
+        bytes.extend_from_slice(chunk);
+        reader.consume(len);
+        if !sniffed && bytes.len() >= BINARY_SNIFF_LEN {
+            sniffed = true;
+            if looks_binary(&bytes) {
+                return Err(TextSourceError::BinaryContent(path.clone()));
             }
-            if rel_path.starts_with(&format!("{}/", trimmed_pat)) {
-                return true;
+        }
+        match std::str::from_utf8(&bytes[validated..]) {
+            Ok(_) => validated = bytes.len(),
+            Err(e) if e.error_len().is_none() => validated += e.valid_up_to(),
+            Err(_) if filter.forces_text(path) => {
+                reader.read_to_end(&mut bytes)?;
+                break;
             }
+            Err(_) => return Err(TextSourceError::NotTextFile(path.clone())),
         }
-        false
     }
+    if validated < bytes.len() {
+        return filter.decode_text(path, bytes);
+    }
+    if !sniffed && looks_binary(&bytes) {
+        return Err(TextSourceError::BinaryContent(path.clone()));
+    }
+    // SAFETY: the loop above validated every byte as UTF-8.
+    Ok(unsafe { String::from_utf8_unchecked(bytes) })
 }
 
 pub struct FileSystemSource {
     pub base_path: PathBuf,
     gitignore_rules: GitIgnoreRules,
+    include_rules: Option<GitIgnoreRules>,
+    ignored: Mutex<Vec<(String, String)>>,
 }
 
 impl FileSystemSource {
@@ -98,53 +172,176 @@ impl FileSystemSource {
         fs::read_dir(&base_path).map_err(|_| {
             TextSourceError::PermissionDenied(base_path.to_string_lossy().to_string())
         })?;
-        let gitignore_rules = GitIgnoreRules::load_from(&base_path);
+        let gitignore_rules = GitIgnoreRules::load_from(&base_path, ".gitignore");
+        let include_rules = base_path
+            .join(CONTEXT_INCLUDE_FILE)
+            .is_file()
+            .then(|| GitIgnoreRules::load_from(&base_path, CONTEXT_INCLUDE_FILE));
         Ok(Self {
             base_path,
             gitignore_rules,
+            include_rules,
+            ignored: Mutex::new(Vec::new()),
         })
     }
+    fn relative_path_str(&self, path: &Path) -> Result<String, TextSourceError> {
+        let rel = path.strip_prefix(&self.base_path).unwrap_or(path);
+        let parts: Option<Vec<&str>> = rel
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect();
+        parts
+            .map(|p| p.join("/"))
+            .ok_or_else(|| TextSourceError::NonUtf8Path(rel.to_string_lossy().into_owned()))
+    }
+    fn excluded_relative_path(&self, filter: &FilterConfig) -> Option<String> {
+        let excluded = filter.exclude_file.as_ref()?;
+        let name = excluded.file_name()?.to_str()?;
+        let parent = match excluded.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize().ok()?,
+            _ => PathBuf::from(".").canonicalize().ok()?,
+        };
+        let base = self.base_path.canonicalize().ok()?;
+        let rel = parent.strip_prefix(&base).ok()?;
+        let mut parts: Vec<&str> = rel
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect::<Option<_>>()?;
+        parts.push(name);
+        Some(parts.join("/"))
+    }
+    fn find_ignoring_case(&self, rel_path: &str) -> Option<String> {
+        let mut dir = self.base_path.clone();
+        let mut found = Vec::new();
+        for part in rel_path.split('/') {
+            let lower = part.to_lowercase();
+            let name = fs::read_dir(&dir)
+                .ok()?
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .find(|name| name.to_lowercase() == lower)?;
+            dir.push(&name);
+            found.push(name);
+        }
+        Some(found.join("/"))
+    }
+    fn collect_listed_files(&self, list: &[String], files: &mut Vec<SourceFile>, filter: &FilterConfig) {
+        for listed_path in list {
+            let mut rel_path = listed_path.clone();
+            let mut path = self.base_path.join(&rel_path);
+            if !path.is_file() && filter.ignore_case {
+                if let Some(found) = self.find_ignoring_case(listed_path) {
+                    path = self.base_path.join(&found);
+                    rel_path = found;
+                }
+            }
+            if !path.is_file() {
+                log::warn!("Listed file not found: {}", path.display());
+                continue;
+            }
+            if filter.skip_empty && fs::metadata(&path).is_ok_and(|m| m.len() == 0) {
+                continue;
+            }
+            files.push(SourceFile {
+                path: rel_path.clone(),
+                source_type: SourceType::FileSystem {
+                    base_path: self.base_path.clone(),
+                    relative_path: PathBuf::from(rel_path),
+                },
+                revision: file_revision(&path),
+            });
+        }
+    }
     fn collect_files(
         &self,
         dir: &Path,
         files: &mut Vec<SourceFile>,
+        ignored: &mut Vec<(String, String)>,
         filter: &FilterConfig,
     ) -> Result<(), TextSourceError> {
         let entries = fs::read_dir(dir)?;
         for entry in entries {
             let entry = entry?;
             let path = entry.path();
-            let rel_path = path
-                .strip_prefix(&self.base_path)
-                .unwrap_or(&path)
-                .to_string_lossy()
-                .into_owned();
+            let rel_path = match self.relative_path_str(&path) {
+                Ok(p) => p,
+                Err(e) => {
+                    log::warn!("Skipping entry: {}", e);
+                    continue;
+                }
+            };
             if let Some(fname) = path.file_name() {
                 let fname_str = fname.to_string_lossy();
-                if fname_str.starts_with('.') {
+                if fname_str.starts_with('.') && (filter.respect_gitignore || fname_str == ".git") {
                     continue;
                 }
                 if fname_str.ends_with('~') {
                     continue;
                 }
             }
-            if self.gitignore_rules.is_ignored(&rel_path) {
-                continue;
-            }
-            if let Some(ext) = get_extension(&rel_path) {
-                if !filter.is_text_extension(ext) {
+            if filter.respect_gitignore {
+                if let Some(pattern) = self.gitignore_rules.matching_pattern(&rel_path, filter) {
+                    let shown = if path.is_dir() { format!("{}/", rel_path) } else { rel_path };
+                    ignored.push((shown, pattern.to_string()));
                     continue;
                 }
             }
+            let included = match &self.include_rules {
+                Some(rules) if path.is_file() => {
+                    if rules.matching_pattern(&rel_path, filter).is_none() {
+                        continue;
+                    }
+                    true
+                }
+                _ => false,
+            };
+            if !included {
+                match get_extension(&rel_path) {
+                    Some(ext) => {
+                        if !filter.is_text_extension(ext) {
+                            continue;
+                        }
+                    }
+                    None => {
+                        if !filter.allows_no_extension() && path.is_file() {
+                            continue;
+                        }
+                    }
+                }
+            }
             if path.is_file() {
+                if filter.skip_empty && fs::metadata(&path).is_ok_and(|m| m.len() == 0) {
+                    continue;
+                }
+                if let Some(window) = filter.modified_within {
+                    let recent = entry
+                        .metadata()
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age <= window);
+                    if !recent {
+                        continue;
+                    }
+                }
+                let content_check = filter.needs_content_check(&rel_path)
+                    || (included && get_extension(&rel_path).is_some_and(|ext| !filter.is_text_extension(ext)));
+                if content_check && file_looks_binary(&path) {
+                    continue;
+                }
                 files.push(SourceFile {
                     path: rel_path,
                     source_type: SourceType::FileSystem {
                         base_path: self.base_path.clone(),
+                        relative_path: path
+                            .strip_prefix(&self.base_path)
+                            .unwrap_or(&path)
+                            .to_path_buf(),
                     },
+                    revision: file_revision(&path),
                 });
             } else if path.is_dir() {
-                self.collect_files(&path, files, filter)?;
+                self.collect_files(&path, files, ignored, filter)?;
             }
         }
         Ok(())
@@ -158,23 +355,205 @@ impl TextSource for FileSystemSource {
         filter: &FilterConfig,
     ) -> Result<Vec<SourceFile>, TextSourceError> {
         let mut files = Vec::new();
-        self.collect_files(&self.base_path, &mut files, filter)?;
+        if let Some(list) = &filter.file_list {
+            self.collect_listed_files(list, &mut files, filter);
+        } else {
+            let mut ignored = Vec::new();
+            self.collect_files(&self.base_path, &mut files, &mut ignored, filter)?;
+            ignored.sort();
+            *self.ignored.lock().unwrap() = ignored;
+        }
+        if let Some(excluded) = self.excluded_relative_path(filter) {
+            files.retain(|f| !is_output_path(&f.path, &excluded));
+        }
         Ok(files)
     }
-    async fn get_file_content(&self, source_file: &SourceFile) -> Result<String, TextSourceError> {
-        if let SourceType::FileSystem { base_path } = &source_file.source_type {
-            let full_path = base_path.join(&source_file.path);
-            if !full_path.exists() {
-                return Err(TextSourceError::PathNotFound(
-                    full_path.to_string_lossy().to_string(),
-                ));
+    async fn get_file_bytes(&self, source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError> {
+        read_file_bytes(source_file)
+    }
+    fn ignored_paths(&self) -> Vec<(String, String)> {
+        self.ignored.lock().unwrap().clone()
+    }
+    async fn get_file_content(
+        &self,
+        source_file: &SourceFile,
+        filter: &FilterConfig,
+    ) -> Result<String, TextSourceError> {
+        let (source_file, filter) = (source_file.clone(), filter.clone());
+        tokio::task::spawn_blocking(move || read_file_text(&source_file, &filter))
+            .await
+            .map_err(|e| TextSourceError::IoError(std::io::Error::other(e)))?
+    }
+    async fn get_file_contents(
+        &self,
+        source_files: &[SourceFile],
+        filter: &FilterConfig,
+    ) -> Vec<Result<String, TextSourceError>> {
+        let shared_filter = Arc::new(filter.clone());
+        let jobs = source_files
+            .iter()
+            .map(|sf| {
+                let sf = sf.clone();
+                let filter = Arc::clone(&shared_filter);
+                async move {
+                    tokio::task::spawn_blocking(move || read_file_text(&sf, &filter))
+                        .await
+                        .map_err(|e| TextSourceError::IoError(std::io::Error::other(e)))?
+                }
+            })
+            .collect();
+        fetch_all(jobs, filter.concurrency).await
+    }
+    fn watch(
+        &self,
+        tx: mpsc::UnboundedSender<PathBuf>,
+    ) -> Result<Option<RecommendedWatcher>, TextSourceError> {
+        let base_path = self.base_path.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                if event.paths.is_empty() {
+                    let _ = tx.send(base_path.clone());
+                }
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
             }
-            let bytes = fs::read(&full_path)?;
-            Ok(String::from_utf8(bytes).map_err(|_| {
-                TextSourceError::NotTextFile(full_path.to_string_lossy().to_string())
-            })?)
-        } else {
-            Err(TextSourceError::InvalidSource)
+        })?;
+        watcher.watch(&self.base_path, RecursiveMode::Recursive)?;
+        Ok(Some(watcher))
+    }
+    fn affects_index(&self, path: &Path, filter: &FilterConfig) -> bool {
+        let rel = match self.base_path.canonicalize() {
+            Ok(base) if path.starts_with(&base) => path.strip_prefix(&base).unwrap_or(path),
+            _ => path.strip_prefix(&self.base_path).unwrap_or(path),
+        };
+        let Some(rel) = rel.to_str().map(|r| r.replace('\\', "/")) else {
+            return true;
+        };
+        if rel.is_empty() {
+            return true;
+        }
+        let hidden = rel
+            .split('/')
+            .any(|part| part == ".git" || (filter.respect_gitignore && part.starts_with('.')) || part.ends_with('~'));
+        if hidden {
+            return false;
         }
+        if filter.respect_gitignore && self.gitignore_rules.matching_pattern(&rel, filter).is_some() {
+            return false;
+        }
+        !self
+            .excluded_relative_path(filter)
+            .is_some_and(|excluded| is_output_path(&rel, &excluded))
+    }
+}
+
+fn is_output_path(path: &str, output: &str) -> bool {
+    if let Some(staged) = strip_staging_suffix(path) {
+        return is_output_path(staged, output);
+    }
+    if path == output || path.strip_prefix(output).is_some_and(|rest| rest.starts_with('/')) {
+        return true;
+    }
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let (output_dir, output_name) = output.rsplit_once('/').unwrap_or(("", output));
+    if dir != output_dir {
+        return false;
+    }
+    let (stem, ext) = match output_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (output_name, None),
+    };
+    let Some(rest) = name.strip_prefix(stem).and_then(|rest| rest.strip_prefix("_part")) else {
+        return false;
+    };
+    let digits = match ext {
+        Some(ext) => rest.strip_suffix(ext).and_then(|r| r.strip_suffix('.')),
+        None => Some(rest),
+    };
+    digits.is_some_and(|d| !d.is_empty() && d.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Strips the `.{pid}-{n}.partial` / `.{pid}-{n}.bak` suffix the output
+/// writer gives its staging files.
+fn strip_staging_suffix(path: &str) -> Option<&str> {
+    let rest = path.strip_suffix(".partial").or_else(|| path.strip_suffix(".bak"))?;
+    let (target, tag) = rest.rsplit_once('.')?;
+    let (pid, n) = tag.split_once('-')?;
+    let numeric = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    (numeric(pid) && numeric(n)).then_some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_output_path, read_file_text, FileSystemSource, FilterConfig, SourceFile, SourceType, TextSource, TextSourceError};
+    use std::fs;
+
+    #[test]
+    fn matches_the_output_and_its_split_parts() {
+        assert!(is_output_path("merged.txt", "merged.txt"));
+        assert!(is_output_path("merged_part1.txt", "merged.txt"));
+        assert!(is_output_path("out/merged_part12.md", "out/merged.md"));
+        assert!(is_output_path("export/src/main.rs", "export"));
+        assert!(is_output_path("notes_part3", "notes"));
+        assert!(is_output_path("merged.txt.4242-0.partial", "merged.txt"));
+        assert!(is_output_path("out/merged_part2.md.4242-3.bak", "out/merged.md"));
+    }
+
+    #[test]
+    fn leaves_other_files_alone() {
+        assert!(!is_output_path("merged_part1.md", "merged.txt"));
+        assert!(!is_output_path("merged_partial.txt", "merged.txt"));
+        assert!(!is_output_path("merged_part.txt", "merged.txt"));
+        assert!(!is_output_path("src/merged_part1.txt", "merged.txt"));
+        assert!(!is_output_path("exports/a.rs", "export"));
+        assert!(!is_output_path("notes.txt.old.bak", "notes.txt"));
+    }
+
+    #[test]
+    fn watch_ignores_git_gitignored_and_output_changes() {
+        let dir = std::env::temp_dir().join(format!("anvil-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        let source = FileSystemSource::new(&dir).unwrap();
+        let mut filter = FilterConfig {
+            exclude_file: Some(dir.join("merged_src.txt")),
+            ..FilterConfig::default()
+        };
+        assert!(source.affects_index(&dir.join("src/main.rs"), &filter));
+        assert!(!source.affects_index(&dir.join(".git/index"), &filter));
+        assert!(!source.affects_index(&dir.join("target/debug/app"), &filter));
+        assert!(!source.affects_index(&dir.join("merged_src.txt"), &filter));
+        assert!(!source.affects_index(&dir.join("merged_src.txt.77-1.partial"), &filter));
+        filter.respect_gitignore = false;
+        assert!(source.affects_index(&dir.join("target/debug/app"), &filter));
+        assert!(!source.affects_index(&dir.join(".git/index"), &filter));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_utf8_split_across_chunks_and_rejects_invalid_text() {
+        let dir = std::env::temp_dir().join(format!("anvil-read-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let text = format!("{}é{}", "a".repeat(super::READ_BUFFER_SIZE - 1), "ü".repeat(1000));
+        fs::write(dir.join("wide.txt"), &text).unwrap();
+        fs::write(dir.join("cut.txt"), &text.as_bytes()[..text.len() - 1]).unwrap();
+        let file = |name: &str| SourceFile {
+            path: name.to_string(),
+            source_type: SourceType::FileSystem {
+                base_path: dir.clone(),
+                relative_path: name.into(),
+            },
+            revision: None,
+        };
+        let filter = FilterConfig::default();
+        assert_eq!(read_file_text(&file("wide.txt"), &filter).unwrap(), text);
+        assert!(matches!(read_file_text(&file("cut.txt"), &filter), Err(TextSourceError::NotTextFile(_))));
+        let _ = fs::remove_dir_all(&dir);
     }
 }
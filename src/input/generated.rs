@@ -0,0 +1,52 @@
+use super::glob::glob_match;
+use std::fs;
+
+const GENERATED_FILE: &str = "generated.txt";
+
+const DEFAULT_GENERATED_PATTERNS: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "Pipfile.lock",
+    "poetry.lock",
+    "uv.lock",
+    "go.sum",
+    "flake.lock",
+    "packages.lock.json",
+    "*.generated.*",
+    "*.min.js",
+    "*.min.css",
+    "*.map",
+    "*.pb.go",
+    "*_pb2.py",
+];
+
+pub fn load_generated_patterns() -> Vec<String> {
+    let custom = crate::config::config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(GENERATED_FILE)).ok());
+    match custom {
+        Some(content) => content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        None => DEFAULT_GENERATED_PATTERNS.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+pub fn is_generated(path: &str, patterns: &[String]) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    patterns.iter().any(|pattern| {
+        if pattern.contains('/') {
+            glob_match(pattern.trim_start_matches('/'), path)
+        } else {
+            glob_match(pattern, name)
+        }
+    })
+}
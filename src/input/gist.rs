@@ -0,0 +1,114 @@
+use super::github::{build_client, fetch_raw, handle_github_response, GitHubConfig};
+use super::{fetch_all, FilterConfig, SourceFile, SourceType, TextSource, TextSourceError};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+pub struct GistSource {
+    pub id: String,
+    pub config: GitHubConfig,
+    pub client: reqwest::Client,
+}
+
+#[derive(serde::Deserialize)]
+struct GistResponse {
+    files: BTreeMap<String, GistFile>,
+}
+
+#[derive(serde::Deserialize)]
+struct GistFile {
+    filename: String,
+    raw_url: String,
+    size: Option<u64>,
+}
+
+impl GistSource {
+    pub fn new(id: String, config: GitHubConfig) -> Self {
+        let client = build_client(&config);
+        Self { id, config, client }
+    }
+    pub fn parse_gist_url(url: &str, prefix: &str) -> Result<String, TextSourceError> {
+        let rest = url
+            .strip_prefix(prefix)
+            .ok_or(TextSourceError::InvalidSource)?;
+        let segments: Vec<&str> = rest
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        match segments.as_slice() {
+            [id] | [_, id, ..] => Ok(id.trim_end_matches(".git").to_string()),
+            [] => Err(TextSourceError::InvalidSource),
+        }
+    }
+}
+
+#[async_trait]
+impl TextSource for GistSource {
+    async fn get_file_index(
+        &self,
+        filter: &FilterConfig,
+    ) -> Result<Vec<SourceFile>, TextSourceError> {
+        let url = format!("{}/gists/{}", self.config.api_base, self.id);
+        let response = self.client.get(&url).send().await?;
+        let gist: GistResponse = handle_github_response(response).await?;
+        let listed: Option<HashSet<String>> = filter
+            .file_list
+            .as_ref()
+            .map(|list| list.iter().map(|p| filter.normalize_name(p)).collect());
+        Ok(gist
+            .files
+            .into_values()
+            .filter(|file| !(filter.skip_empty && file.size == Some(0)))
+            .filter(|file| {
+                if let Some(listed) = &listed {
+                    return listed.contains(&filter.normalize_name(&file.filename));
+                }
+                if let Some(ext) = crate::input::file_system::get_extension(&file.filename) {
+                    filter.is_text_extension(ext)
+                } else {
+                    filter.allows_no_extension()
+                }
+            })
+            .map(|file| SourceFile {
+                path: file.filename,
+                revision: Some(file.raw_url.clone()),
+                source_type: SourceType::Gist {
+                    raw_url: file.raw_url,
+                },
+            })
+            .collect())
+    }
+    async fn get_file_bytes(&self, source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError> {
+        let SourceType::Gist { raw_url } = &source_file.source_type else {
+            return Err(TextSourceError::InvalidSource);
+        };
+        fetch_raw(self.client.clone(), raw_url.clone(), source_file.path.clone()).await
+    }
+    async fn get_file_contents(
+        &self,
+        source_files: &[SourceFile],
+        filter: &FilterConfig,
+    ) -> Vec<Result<String, TextSourceError>> {
+        let shared_filter = Arc::new(filter.clone());
+        let jobs = source_files
+            .iter()
+            .map(|sf| {
+                let client = self.client.clone();
+                let raw_url = match &sf.source_type {
+                    SourceType::Gist { raw_url } => Ok(raw_url.clone()),
+                    _ => Err(TextSourceError::InvalidSource),
+                };
+                let path = sf.path.clone();
+                let filter = Arc::clone(&shared_filter);
+                async move {
+                    let bytes = fetch_raw(client, raw_url?, path.clone()).await?;
+                    filter.decode_text_async(&path, bytes).await
+                }
+            })
+            .collect();
+        fetch_all(jobs, filter.concurrency).await
+    }
+}
@@ -1,35 +1,373 @@
-use super::{FilterConfig, SourceFile, TextSource, TextSourceError};
+use super::{fetch_all, join_url_path, ChangeScope, FilterConfig, SourceFile, SourceType, Submodule, TextSource, TextSourceError};
+use crate::config::{env_value, home_dir};
 use async_trait::async_trait;
+use once_cell::sync::Lazy;
 use reqwest;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, ETAG, IF_NONE_MATCH};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use url::Url;
 
+#[derive(Debug, Clone)]
+pub struct GitHubConfig {
+    pub host: String,
+    pub api_base: String,
+    pub raw_base: String,
+    pub user_agent: String,
+    pub timeout: Duration,
+    pub token: Option<String>,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            host: "github.com".to_string(),
+            api_base: "https://api.github.com".to_string(),
+            raw_base: "https://raw.githubusercontent.com".to_string(),
+            user_agent: "rust-text-source".to_string(),
+            timeout: Duration::from_secs(30),
+            token: None,
+        }
+    }
+}
+
+impl GitHubConfig {
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(host) = env_value("ANVIL_GITHUB_HOST") {
+            config.api_base = format!("https://{}/api/v3", host);
+            config.raw_base = format!("https://{}/raw", host);
+            config.host = host;
+        }
+        if let Some(api_base) = env_value("ANVIL_GITHUB_API_URL") {
+            config.api_base = api_base.trim_end_matches('/').to_string();
+        }
+        if let Some(raw_base) = env_value("ANVIL_GITHUB_RAW_URL") {
+            config.raw_base = raw_base.trim_end_matches('/').to_string();
+        }
+        if let Some(user_agent) = env_value("ANVIL_USER_AGENT") {
+            config.user_agent = user_agent;
+        }
+        if let Some(secs) = env_value("ANVIL_GITHUB_TIMEOUT").and_then(|v| v.parse().ok()) {
+            config.timeout = Duration::from_secs(secs);
+        }
+        config.token = env_value("GITHUB_TOKEN")
+            .or_else(|| env_value("GH_TOKEN"))
+            .or_else(|| gh_hosts_token(&config.host))
+            .or_else(|| netrc_token(&config.host));
+        config
+    }
+    pub fn web_prefix(&self) -> String {
+        format!("https://{}", self.host)
+    }
+    pub fn gist_prefix(&self) -> String {
+        if self.host == "github.com" {
+            "https://gist.github.com".to_string()
+        } else {
+            format!("https://{}/gist", self.host)
+        }
+    }
+}
+
+fn gh_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env_value("GH_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = env_value("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("gh"));
+    }
+    if cfg!(windows) {
+        if let Some(dir) = env_value("APPDATA") {
+            return Some(PathBuf::from(dir).join("GitHub CLI"));
+        }
+    }
+    home_dir().map(|home| home.join(".config").join("gh"))
+}
+
+fn gh_hosts_token(host: &str) -> Option<String> {
+    let content = std::fs::read_to_string(gh_config_dir()?.join("hosts.yml")).ok()?;
+    let mut in_host = false;
+    for line in content.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t']) {
+            in_host = line.trim_end().trim_end_matches(':').trim_matches(['"', '\'']) == host;
+            continue;
+        }
+        if in_host {
+            if let Some(token) = line.trim().strip_prefix("oauth_token:") {
+                let token = token.trim().trim_matches(['"', '\'']);
+                if !token.is_empty() {
+                    return Some(token.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+fn netrc_token(host: &str) -> Option<String> {
+    let home = home_dir()?;
+    let content = std::fs::read_to_string(home.join(".netrc"))
+        .or_else(|_| std::fs::read_to_string(home.join("_netrc")))
+        .ok()?;
+    let api_host = if host == "github.com" { "api.github.com" } else { host };
+    let mut words = content.split_whitespace();
+    let mut machine = None;
+    while let Some(word) = words.next() {
+        match word {
+            "machine" => machine = words.next(),
+            "default" => machine = None,
+            "password" => {
+                let password = words.next();
+                if machine == Some(host) || machine == Some(api_host) {
+                    return password.map(|p| p.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+pub(super) static API_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+const RAW_CACHE_BYTES: usize = 64 * 1024 * 1024;
+static RAW_CACHE: Lazy<Mutex<EtagCache>> = Lazy::new(|| Mutex::new(EtagCache::new(RAW_CACHE_BYTES)));
+
+/// Raw file bodies keyed by URL, evicting the least recently used entries
+/// once the stored bodies exceed `budget` bytes.
+struct EtagCache {
+    entries: HashMap<String, CachedBody>,
+    recency: BTreeMap<u64, String>,
+    generation: u64,
+    bytes: usize,
+    budget: usize,
+}
+
+struct CachedBody {
+    etag: String,
+    body: Vec<u8>,
+    generation: u64,
+}
+
+impl EtagCache {
+    fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            generation: 0,
+            bytes: 0,
+            budget,
+        }
+    }
+
+    fn etag(&mut self, url: &str) -> Option<String> {
+        self.touch(url);
+        self.entries.get(url).map(|entry| entry.etag.clone())
+    }
+
+    fn body(&mut self, url: &str) -> Option<Vec<u8>> {
+        self.touch(url);
+        self.entries.get(url).map(|entry| entry.body.clone())
+    }
+
+    fn insert(&mut self, url: String, etag: String, body: Vec<u8>) {
+        self.remove(&url);
+        if body.len() > self.budget {
+            return;
+        }
+        self.generation += 1;
+        self.bytes += body.len();
+        self.recency.insert(self.generation, url.clone());
+        self.entries.insert(url, CachedBody { etag, body, generation: self.generation });
+        while self.bytes > self.budget {
+            let Some((_, oldest)) = self.recency.pop_first() else { break };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.bytes -= entry.body.len();
+            }
+        }
+    }
+
+    fn touch(&mut self, url: &str) {
+        let Some(entry) = self.entries.get_mut(url) else {
+            return;
+        };
+        self.generation += 1;
+        if let Some(url) = self.recency.remove(&entry.generation) {
+            self.recency.insert(self.generation, url);
+        }
+        entry.generation = self.generation;
+    }
+
+    fn remove(&mut self, url: &str) {
+        if let Some(entry) = self.entries.remove(url) {
+            self.bytes -= entry.body.len();
+            self.recency.remove(&entry.generation);
+        }
+    }
+}
+
+pub fn api_request_count() -> usize {
+    API_REQUESTS.load(Ordering::Relaxed)
+}
+
+pub(super) fn build_client(config: &GitHubConfig) -> reqwest::Client {
+    build_http_client(&config.user_agent, config.timeout, config.token.as_deref())
+}
+
+pub(super) fn build_http_client(user_agent: &str, timeout: Duration, token: Option<&str>) -> reqwest::Client {
+    let mut headers = HeaderMap::new();
+    if let Some(token) = token {
+        if let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+    }
+    reqwest::Client::builder()
+        .user_agent(user_agent)
+        .default_headers(headers)
+        .timeout(timeout)
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .build()
+        .unwrap_or_default()
+}
+
+pub(super) fn rate_limit_reset(response: &reqwest::Response) -> Option<u64> {
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    if let Some(secs) = header("retry-after") {
+        return Some(secs);
+    }
+    if header("x-ratelimit-remaining") != Some(0) {
+        return None;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    header("x-ratelimit-reset").map(|reset| reset.saturating_sub(now))
+}
+
+pub(super) async fn handle_github_response<T: for<'de> serde::Deserialize<'de>>(
+    response: reqwest::Response,
+) -> Result<T, TextSourceError> {
+    API_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    let status = response.status();
+    log::info!("GitHub API {} -> {}", response.url(), status);
+    let reset = rate_limit_reset(&response);
+    let text = response
+        .text()
+        .await?;
+    if status.is_success() {
+        Ok(serde_json::from_str::<T>(&text)
+            .map_err(|e| TextSourceError::GitHubError(e.to_string()))?)
+    } else if status.as_u16() == 403 || status.as_u16() == 429 {
+        Err(TextSourceError::RateLimitExceeded(reset))
+    } else if status.as_u16() == 404 {
+        Err(TextSourceError::RepoNotFound)
+    } else {
+        Err(TextSourceError::GitHubError(text))
+    }
+}
+
+pub(super) async fn fetch_raw(
+    client: reqwest::Client,
+    url: String,
+    path: String,
+) -> Result<Vec<u8>, TextSourceError> {
+    let cached_etag = RAW_CACHE.lock().ok().and_then(|mut cache| cache.etag(&url));
+    fetch_raw_with(client, url, path, cached_etag).await
+}
+
+async fn fetch_raw_with(
+    client: reqwest::Client,
+    url: String,
+    path: String,
+    cached_etag: Option<String>,
+) -> Result<Vec<u8>, TextSourceError> {
+    let started = Instant::now();
+    API_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    let mut request = client.get(&url);
+    if let Some(etag) = &cached_etag {
+        request = request.header(IF_NONE_MATCH, etag.as_str());
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    let reset = rate_limit_reset(&response);
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let bytes = response.bytes().await?;
+    log::debug!("GET {} -> {} ({} bytes in {:?})", url, status, bytes.len(), started.elapsed());
+    if status.as_u16() == 304 && cached_etag.is_some() {
+        // The body may have been evicted while the request was in flight.
+        return match RAW_CACHE.lock().ok().and_then(|mut cache| cache.body(&url)) {
+            Some(content) => Ok(content),
+            None => Box::pin(fetch_raw_with(client, url, path, None)).await,
+        };
+    }
+    if status.is_success() {
+        let body = bytes.to_vec();
+        if let (Some(etag), Ok(mut cache)) = (etag, RAW_CACHE.lock()) {
+            cache.insert(url, etag, body.clone());
+        }
+        Ok(body)
+    } else if status.as_u16() == 404 {
+        Err(TextSourceError::PathNotFound(path))
+    } else if status.as_u16() == 403 || status.as_u16() == 429 {
+        Err(TextSourceError::RateLimitExceeded(reset))
+    } else {
+        Err(TextSourceError::GitHubError(status.to_string()))
+    }
+}
+
 pub struct GitHubSource {
     pub owner: String,
     pub repo: String,
     pub branch: String,
     pub subpath: Option<String>,
+    pub config: GitHubConfig,
     pub client: reqwest::Client,
+    submodules: Mutex<Vec<Submodule>>,
+    index_warnings: Mutex<Vec<String>>,
 }
 
 impl GitHubSource {
-    pub fn new(owner: String, repo: String, branch: String, subpath: Option<String>) -> Self {
-        let client = reqwest::Client::builder()
-            .user_agent("rust-text-source")
-            .build()
-            .unwrap_or_default();
+    pub fn new(
+        owner: String,
+        repo: String,
+        branch: String,
+        subpath: Option<String>,
+        config: GitHubConfig,
+    ) -> Self {
+        let client = build_client(&config);
         Self {
             owner,
             repo,
             branch,
             subpath,
+            config,
             client,
+            submodules: Mutex::new(Vec::new()),
+            index_warnings: Mutex::new(Vec::new()),
         }
     }
     pub fn parse_github_url(
         url: &str,
+        host: &str,
     ) -> Result<(String, String, String, Option<String>), TextSourceError> {
         let parsed = Url::parse(url).map_err(|_| TextSourceError::InvalidSource)?;
-        if parsed.scheme() != "https" || parsed.host_str() != Some("github.com") {
+        if parsed.scheme() != "https" || parsed.host_str() != Some(host) {
             return Err(TextSourceError::InvalidSource);
         }
         let segments: Vec<&str> = parsed
@@ -52,28 +390,239 @@ impl GitHubSource {
         }
         Ok((owner, repository, branch, subpath))
     }
-    async fn handle_github_response<T: for<'de> serde::Deserialize<'de>>(
+    async fn get_json<T: for<'de> serde::Deserialize<'de>>(
         &self,
-        response: reqwest::Response,
+        url: &str,
     ) -> Result<T, TextSourceError> {
-        let status = response.status();
-        let text = response
-            .text()
-            .await
-            .map_err(TextSourceError::NetworkError)?;
-        if status.is_success() {
-            Ok(serde_json::from_str::<T>(&text)
-                .map_err(|e| TextSourceError::GitHubError(e.to_string()))?)
-        } else if status.as_u16() == 403 {
-            Err(TextSourceError::RateLimitExceeded)
-        } else if status.as_u16() == 404 {
-            Err(TextSourceError::RepoNotFound)
+        let response = self.client.get(url).send().await?;
+        handle_github_response(response).await
+    }
+    fn source_type(&self) -> SourceType {
+        SourceType::GitHub {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            branch: self.branch.clone(),
+        }
+    }
+    fn relative_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        match &self.subpath {
+            Some(sp) => path.strip_prefix(sp.as_str()).map(|p| p.trim_start_matches('/')),
+            None => Some(path),
+        }
+    }
+    fn repo_path(&self, source_file: &SourceFile, owner: &str, repo: &str, branch: &str) -> String {
+        let full_path = match self.subpath.as_ref() {
+            Some(sp) => format!("{}/{}", sp, source_file.path),
+            None => source_file.path.clone(),
+        };
+        if owner == self.owner && repo == self.repo && branch == self.branch {
+            return full_path;
+        }
+        let repository = format!("{}/{}", owner, repo);
+        let submodules = self.submodules.lock().unwrap();
+        submodules
+            .iter()
+            .filter(|s| s.included && s.commit == branch && s.repository.as_deref() == Some(repository.as_str()))
+            .find_map(|s| full_path.strip_prefix(&format!("{}/", s.root)).map(|p| p.to_string()))
+            .unwrap_or(full_path)
+    }
+    fn submodule_repository(&self, url: &str) -> Option<String> {
+        let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+        let path = if let Some(rest) = url.strip_prefix("../../") {
+            rest.to_string()
+        } else if let Some(rest) = url.strip_prefix("../") {
+            format!("{}/{}", self.owner, rest)
+        } else if let Some(rest) = url.strip_prefix(&format!("git@{}:", self.config.host)) {
+            rest.to_string()
         } else {
-            Err(TextSourceError::GitHubError(text))
+            let parsed = Url::parse(url).ok()?;
+            if parsed.host_str() != Some(self.config.host.as_str()) {
+                return None;
+            }
+            parsed.path().trim_start_matches('/').to_string()
+        };
+        let mut segments = path.split('/');
+        match (segments.next(), segments.next(), segments.next()) {
+            (Some(owner), Some(repo), None) if !owner.is_empty() && !repo.is_empty() => {
+                Some(format!("{}/{}", owner, repo))
+            }
+            _ => None,
+        }
+    }
+    async fn submodule_repositories(&self) -> HashMap<String, String> {
+        let url = format!(
+            "{}/{}/{}/{}/.gitmodules",
+            self.config.raw_base, self.owner, self.repo, self.branch
+        );
+        let Ok(bytes) = fetch_raw(self.client.clone(), url, ".gitmodules".to_string()).await else {
+            return HashMap::new();
+        };
+        parse_gitmodules(&String::from_utf8_lossy(&bytes))
+            .into_iter()
+            .filter_map(|(path, url)| self.submodule_repository(&url).map(|repository| (path, repository)))
+            .collect()
+    }
+    async fn index_submodules(
+        &self,
+        commits: Vec<GitHubContent>,
+        filter: &FilterConfig,
+        entries: &mut Vec<(String, GitHubContent, SourceType)>,
+    ) -> Result<(), TextSourceError> {
+        let repositories = self.submodule_repositories().await;
+        let mut submodules = Vec::new();
+        for item in commits {
+            let Some(commit) = item.sha else {
+                continue;
+            };
+            let repository = repositories.get(&item.path).cloned();
+            let mut submodule = Submodule {
+                path: item.path.clone(),
+                root: item.path,
+                repository,
+                commit,
+                included: false,
+            };
+            if let (true, Some((owner, repo))) = (
+                filter.include_submodules,
+                submodule.repository.as_deref().and_then(|r| r.split_once('/')),
+            ) {
+                let url = format!(
+                    "{}/repos/{}/{}/git/trees/{}?recursive=1",
+                    self.config.api_base, owner, repo, submodule.commit
+                );
+                match self.get_json::<GitHubTreeResponse>(&url).await {
+                    Ok(tree_response) => {
+                        let source_type = SourceType::GitHub {
+                            owner: owner.to_string(),
+                            repo: repo.to_string(),
+                            branch: submodule.commit.clone(),
+                        };
+                        entries.extend(
+                            tree_response
+                                .tree
+                                .into_iter()
+                                .filter(|entry| entry.r#type == "blob")
+                                .map(|entry| {
+                                    (format!("{}/{}", submodule.root, entry.path), entry, source_type.clone())
+                                }),
+                        );
+                        submodule.included = true;
+                    }
+                    Err(e @ TextSourceError::RateLimitExceeded(_)) => return Err(e),
+                    Err(e) => log::warn!("Could not index submodule {}: {}", submodule.path, e),
+                }
+            }
+            if let Some(relative) = self.relative_path(&submodule.root).filter(|p| !p.is_empty()) {
+                submodule.path = relative.to_string();
+            }
+            submodules.push(submodule);
+        }
+        *self.submodules.lock().unwrap() = submodules;
+        Ok(())
+    }
+    fn raw_url(&self, source_file: &SourceFile) -> Result<String, TextSourceError> {
+        let super::SourceType::GitHub { owner, repo, branch } = &source_file.source_type else {
+            return Err(TextSourceError::InvalidSource);
+        };
+        let file_path = self.repo_path(source_file, owner, repo, branch);
+        let base = format!("{}/{}/{}/{}", self.config.raw_base, owner, repo, branch);
+        Ok(join_url_path(&base, &file_path))
+    }
+    async fn get_changed_paths(&self, scope: &ChangeScope) -> Result<HashSet<String>, TextSourceError> {
+        let repo_url = format!("{}/repos/{}/{}", self.config.api_base, self.owner, self.repo);
+        let mut changed = HashSet::new();
+        let mut truncated = Vec::new();
+        match scope {
+            ChangeScope::LastCommits(n) => {
+                let per_page = (*n).min(GITHUB_MAX_PER_PAGE);
+                let mut commits: Vec<GitHubCommitRef> = Vec::new();
+                for page in 1.. {
+                    let batch: Vec<GitHubCommitRef> = self
+                        .get_json(&format!(
+                            "{}/commits?sha={}&per_page={}&page={}",
+                            repo_url, self.branch, per_page, page
+                        ))
+                        .await?;
+                    let last = batch.len() < per_page;
+                    commits.extend(batch);
+                    if last || commits.len() >= *n {
+                        break;
+                    }
+                }
+                commits.truncate(*n);
+                for commit in commits {
+                    let detail: GitHubChangedFiles = self
+                        .get_json(&format!("{}/commits/{}", repo_url, commit.sha))
+                        .await?;
+                    if detail.files.len() >= GITHUB_MAX_CHANGED_FILES {
+                        truncated.push(commit.sha.chars().take(7).collect::<String>());
+                    }
+                    changed.extend(detail.files.into_iter().map(|f| f.filename));
+                }
+            }
+            ChangeScope::SinceRef(base) => {
+                let compare: GitHubChangedFiles = self
+                    .get_json(&format!("{}/compare/{}...{}", repo_url, base, self.branch))
+                    .await?;
+                if compare.files.len() >= GITHUB_MAX_CHANGED_FILES {
+                    truncated.push(format!("{}...{}", base, self.branch));
+                }
+                changed.extend(compare.files.into_iter().map(|f| f.filename));
+            }
+        }
+        if !truncated.is_empty() {
+            self.index_warnings.lock().unwrap().push(format!(
+                "GitHub lists at most {} changed files per commit or comparison, so changes in {} may be missing",
+                GITHUB_MAX_CHANGED_FILES,
+                truncated.join(", ")
+            ));
         }
+        Ok(changed)
     }
 }
 
+const GITHUB_MAX_PER_PAGE: usize = 100;
+const GITHUB_MAX_CHANGED_FILES: usize = 300;
+
+#[derive(serde::Deserialize)]
+struct GitHubCommitRef {
+    sha: String,
+}
+#[derive(serde::Deserialize)]
+struct GitHubChangedFiles {
+    #[serde(default)]
+    files: Vec<GitHubChangedFile>,
+}
+#[derive(serde::Deserialize)]
+struct GitHubChangedFile {
+    filename: String,
+}
+
+fn parse_gitmodules(content: &str) -> Vec<(String, String)> {
+    let mut modules = Vec::new();
+    let mut path = None;
+    let mut url = None;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            if let (Some(p), Some(u)) = (path.take(), url.take()) {
+                modules.push((p, u));
+            }
+            path = None;
+            url = None;
+        } else if let Some((key, value)) = line.split_once('=') {
+            match key.trim() {
+                "path" => path = Some(value.trim().trim_end_matches('/').to_string()),
+                "url" => url = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+    if let (Some(p), Some(u)) = (path, url) {
+        modules.push((p, u));
+    }
+    modules
+}
+
 #[derive(serde::Deserialize)]
 struct GitHubTreeResponse {
     tree: Vec<GitHubContent>,
@@ -82,6 +631,8 @@ struct GitHubTreeResponse {
 struct GitHubContent {
     path: String,
     r#type: String,
+    sha: Option<String>,
+    size: Option<u64>,
 }
 
 #[async_trait]
@@ -91,92 +642,129 @@ impl TextSource for GitHubSource {
         filter: &FilterConfig,
     ) -> Result<Vec<SourceFile>, TextSourceError> {
         let url = format!(
-            "https://api.github.com/repos/{}/{}/git/trees/{}?recursive=1",
-            self.owner, self.repo, self.branch
+            "{}/repos/{}/{}/git/trees/{}?recursive=1",
+            self.config.api_base, self.owner, self.repo, self.branch
         );
         let response = self.client.get(&url).send().await?;
-        let tree_response: GitHubTreeResponse = self.handle_github_response(response).await?;
-        let filtered_contents = tree_response
-            .tree
+        let tree_response: GitHubTreeResponse = handle_github_response(response).await?;
+        let listed: Option<HashSet<String>> = filter
+            .file_list
+            .as_ref()
+            .map(|list| list.iter().map(|p| filter.normalize_name(p)).collect());
+        self.index_warnings.lock().unwrap().clear();
+        let changed = match &filter.changed_only {
+            Some(scope) => Some(self.get_changed_paths(scope).await?),
+            None => None,
+        };
+        let mut entries = Vec::new();
+        let mut commits = Vec::new();
+        for item in tree_response.tree {
+            let contains_subpath = self
+                .subpath
+                .as_ref()
+                .is_some_and(|sp| sp.starts_with(&format!("{}/", item.path)));
+            if self.relative_path(&item.path).is_none() && !(item.r#type == "commit" && contains_subpath) {
+                continue;
+            }
+            match item.r#type.as_str() {
+                "blob" => entries.push((item.path.clone(), item, self.source_type())),
+                "commit" => commits.push(item),
+                _ => {}
+            }
+        }
+        if commits.is_empty() {
+            self.submodules.lock().unwrap().clear();
+        } else {
+            self.index_submodules(commits, filter, &mut entries).await?;
+        }
+        let filtered_contents = entries
             .into_iter()
-            .filter(|item| item.r#type == "blob")
-            .filter(|item| {
-                if let Some(sp) = &self.subpath {
-                    if !item.path.starts_with(sp) {
-                        return false;
-                    }
-                }
-                true
-            })
-            .filter(|item| {
-                let path_relative = if let Some(sp) = &self.subpath {
-                    if let Some(stripped) = item.path.strip_prefix(sp) {
-                        stripped.trim_start_matches('/')
-                    } else {
-                        &item.path
-                    }
-                } else {
-                    &item.path
-                };
-                if let Some(ext) = crate::input::file_system::get_extension(path_relative) {
+            .filter(|(_, item, _)| !(filter.skip_empty && item.size == Some(0)))
+            .filter(|(path, _, _)| changed.as_ref().is_none_or(|c| c.contains(path)))
+            .filter_map(|(path, item, source_type)| {
+                let path_relative = self.relative_path(&path)?;
+                let keep = if let Some(listed) = &listed {
+                    listed.contains(&filter.normalize_name(path_relative))
+                } else if let Some(ext) = crate::input::file_system::get_extension(path_relative) {
                     filter.is_text_extension(ext)
                 } else {
-                    true
-                }
-            })
-            .map(|item| {
-                let path_str = if let Some(sp) = &self.subpath {
-                    if let Some(stripped) = item.path.strip_prefix(sp) {
-                        stripped.trim_start_matches('/').to_string()
-                    } else {
-                        item.path
-                    }
-                } else {
-                    item.path
+                    filter.allows_no_extension()
                 };
-                SourceFile {
-                    path: path_str,
-                    source_type: super::SourceType::GitHub {
-                        owner: self.owner.clone(),
-                        repo: self.repo.clone(),
-                        branch: self.branch.clone(),
-                    },
-                }
+                keep.then(|| SourceFile {
+                    path: path_relative.to_string(),
+                    source_type,
+                    revision: item.sha,
+                })
             })
             .collect();
         Ok(filtered_contents)
     }
-    async fn get_file_content(&self, source_file: &SourceFile) -> Result<String, TextSourceError> {
-        match &source_file.source_type {
-            super::SourceType::GitHub {
-                owner,
-                repo,
-                branch,
-            } => {
-                let file_path = if let Some(sp) = self.subpath.as_ref() {
-                    format!("{}/{}", sp, source_file.path)
-                } else {
-                    source_file.path.clone()
-                };
-                let raw_url = format!(
-                    "https://raw.githubusercontent.com/{}/{}/{}/{}",
-                    owner, repo, branch, file_path
-                );
-                let response = self.client.get(&raw_url).send().await?;
-                let status = response.status();
-                let bytes = response.bytes().await?;
-                if status.is_success() {
-                    Ok(String::from_utf8(bytes.to_vec())
-                        .map_err(|_| TextSourceError::NotTextFile(file_path))?)
-                } else if status.as_u16() == 404 {
-                    Err(TextSourceError::PathNotFound(source_file.path.clone()))
-                } else if status.as_u16() == 403 {
-                    Err(TextSourceError::RateLimitExceeded)
-                } else {
-                    Err(TextSourceError::GitHubError(status.to_string()))
+    fn submodules(&self) -> Vec<Submodule> {
+        self.submodules.lock().unwrap().clone()
+    }
+    fn index_warnings(&self) -> Vec<String> {
+        self.index_warnings.lock().unwrap().clone()
+    }
+    fn web_url(&self, source_file: &SourceFile) -> Option<String> {
+        let super::SourceType::GitHub { owner, repo, branch } = &source_file.source_type else {
+            return None;
+        };
+        let file_path = self.repo_path(source_file, owner, repo, branch);
+        let base = format!("{}/{}/{}/blob/{}", self.config.web_prefix(), owner, repo, branch);
+        Some(join_url_path(&base, &file_path))
+    }
+    async fn get_file_bytes(&self, source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError> {
+        let url = self.raw_url(source_file)?;
+        fetch_raw(self.client.clone(), url, source_file.path.clone()).await
+    }
+    async fn get_file_contents(
+        &self,
+        source_files: &[SourceFile],
+        filter: &FilterConfig,
+    ) -> Vec<Result<String, TextSourceError>> {
+        let shared_filter = Arc::new(filter.clone());
+        let jobs = source_files
+            .iter()
+            .map(|sf| {
+                let client = self.client.clone();
+                let url = self.raw_url(sf);
+                let path = sf.path.clone();
+                let filter = Arc::clone(&shared_filter);
+                async move {
+                    let bytes = fetch_raw(client, url?, path.clone()).await?;
+                    filter.decode_text_async(&path, bytes).await
                 }
-            }
-            _ => Err(TextSourceError::InvalidSource),
-        }
+            })
+            .collect();
+        fetch_all(jobs, filter.concurrency).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EtagCache;
+
+    #[test]
+    fn raw_cache_evicts_least_recently_used_over_budget() {
+        let mut cache = EtagCache::new(10);
+        cache.insert("a".into(), "1".into(), vec![0; 4]);
+        cache.insert("b".into(), "2".into(), vec![0; 4]);
+        assert_eq!(cache.etag("a").as_deref(), Some("1"));
+        cache.insert("c".into(), "3".into(), vec![0; 4]);
+        assert!(cache.etag("b").is_none());
+        assert!(cache.body("a").is_some());
+        assert!(cache.body("c").is_some());
+        assert_eq!(cache.recency.len(), 2);
+        assert_eq!(cache.bytes, 8);
+    }
+
+    #[test]
+    fn raw_cache_skips_bodies_larger_than_budget() {
+        let mut cache = EtagCache::new(10);
+        cache.insert("a".into(), "1".into(), vec![0; 4]);
+        cache.insert("a".into(), "2".into(), vec![0; 11]);
+        assert!(cache.etag("a").is_none());
+        assert_eq!(cache.bytes, 0);
+        assert!(cache.recency.is_empty());
     }
 }
@@ -0,0 +1,160 @@
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    Matcher::new(&pattern, &text).match_from(0, 0)
+}
+
+struct Matcher<'a> {
+    p: &'a [char],
+    t: &'a [char],
+    memo: Vec<Option<bool>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(p: &'a [char], t: &'a [char]) -> Self {
+        Self {
+            p,
+            t,
+            memo: vec![None; (p.len() + 1) * (t.len() + 1)],
+        }
+    }
+
+    fn match_from(&mut self, pi: usize, ti: usize) -> bool {
+        let key = pi * (self.t.len() + 1) + ti;
+        if let Some(matched) = self.memo[key] {
+            return matched;
+        }
+        let matched = self.match_uncached(pi, ti);
+        self.memo[key] = Some(matched);
+        matched
+    }
+
+    fn match_uncached(&mut self, pi: usize, ti: usize) -> bool {
+        let (p, t) = (self.p, self.t);
+        match p.get(pi) {
+            None => ti == t.len(),
+            Some('*') if p.get(pi + 1) == Some(&'*') => {
+                let rest = pi + 2;
+                if p.get(rest) == Some(&'/') && self.match_from(rest + 1, ti) {
+                    return true;
+                }
+                (ti..=t.len()).any(|i| self.match_from(rest, i))
+            }
+            Some('*') => {
+                for i in ti..=t.len() {
+                    if self.match_from(pi + 1, i) {
+                        return true;
+                    }
+                    if t.get(i) == Some(&'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            Some('?') => t.get(ti).is_some_and(|&c| c != '/') && self.match_from(pi + 1, ti + 1),
+            Some('[') => match match_class(&p[pi..], t.get(ti).copied()) {
+                Some((matched, len)) => matched && self.match_from(pi + len, ti + 1),
+                None => t.get(ti) == Some(&'[') && self.match_from(pi + 1, ti + 1),
+            },
+            Some('\\') if pi + 1 < p.len() => t.get(ti) == Some(&p[pi + 1]) && self.match_from(pi + 2, ti + 1),
+            Some(c) => t.get(ti) == Some(c) && self.match_from(pi + 1, ti + 1),
+        }
+    }
+}
+
+fn match_class(p: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(p.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let start = *p.get(i)?;
+        if start == ']' && !first {
+            break;
+        }
+        first = false;
+        if p.get(i + 1) == Some(&'-') && p.get(i + 2).is_some_and(|&end| end != ']') {
+            let end = p[i + 2];
+            matched |= c.is_some_and(|c| start <= c && c <= end);
+            i += 3;
+        } else {
+            matched |= c == Some(start);
+            i += 1;
+        }
+    }
+    let matched = c.is_some_and(|c| c != '/') && matched != negated;
+    Some((matched, i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn question_mark_matches_one_char_but_not_slash() {
+        assert!(glob_match("file?.rs", "file1.rs"));
+        assert!(!glob_match("file?.rs", "file.rs"));
+        assert!(!glob_match("file?.rs", "file12.rs"));
+        assert!(!glob_match("a?b", "a/b"));
+    }
+
+    #[test]
+    fn character_sets() {
+        assert!(glob_match("[abc].txt", "b.txt"));
+        assert!(!glob_match("[abc].txt", "d.txt"));
+        assert!(glob_match("*.[oa]", "lib.a"));
+        assert!(glob_match("]", "]"));
+        assert!(glob_match("[]]", "]"));
+    }
+
+    #[test]
+    fn character_ranges() {
+        assert!(glob_match("v[0-9].md", "v7.md"));
+        assert!(!glob_match("v[0-9].md", "vx.md"));
+        assert!(glob_match("[a-c-]", "-"));
+    }
+
+    #[test]
+    fn negated_sets() {
+        assert!(glob_match("[!x]y", "ay"));
+        assert!(!glob_match("[!x]y", "xy"));
+        assert!(glob_match("[^a-z]1", "Q1"));
+        assert!(!glob_match("[!x]", "/"));
+        assert!(!glob_match("[!x]", ""));
+    }
+
+    #[test]
+    fn star_stays_within_a_segment() {
+        assert!(glob_match("src/*.rs", "src/main.rs"));
+        assert!(!glob_match("src/*.rs", "src/ui/mod.rs"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn double_star_crosses_segments() {
+        assert!(glob_match("src/**/*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/ui/panels/mod.rs"));
+        assert!(glob_match("**/test_*", "a/b/test_x"));
+        assert!(glob_match("**", "any/path/at/all"));
+        assert!(!glob_match("src/**/*.rs", "lib/main.rs"));
+    }
+
+    #[test]
+    fn escapes_and_unclosed_brackets() {
+        assert!(glob_match(r"\*.md", "*.md"));
+        assert!(!glob_match(r"\*.md", "a.md"));
+        assert!(glob_match(r"a\?", "a?"));
+        assert!(glob_match(r"\[x]", "[x]"));
+        assert!(glob_match("[abc", "[abc"));
+    }
+
+    #[test]
+    fn pathological_patterns_finish_quickly() {
+        let text = "a".repeat(60);
+        assert!(!glob_match("*a*a*a*a*a*a*a*a*b", &text));
+        assert!(!glob_match("**a**a**a**a**a**a**b", &text));
+    }
+}
@@ -1,12 +1,23 @@
+pub mod bitbucket;
+pub mod convert;
 pub mod file_system;
+pub mod generated;
+pub mod gist;
+pub mod glob;
 pub mod github;
+pub mod multi;
+pub mod stdin;
 pub mod tokenizer;
 use async_trait::async_trait;
-use once_cell::sync::Lazy;
-use std::collections::HashSet;
+use notify::RecommendedWatcher;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
 
-pub use tokenizer::count_tokens_in_content;
+pub use tokenizer::{count_tokens_in_content, TokenizerModel};
 
 #[derive(Error, Debug)]
 pub enum TextSourceError {
@@ -16,18 +27,44 @@ pub enum TextSourceError {
     IoError(#[from] std::io::Error),
     #[error("GitHub API error: {0}")]
     GitHubError(String),
+    #[error("Bitbucket API error: {0}")]
+    BitbucketError(String),
     #[error("Network error: {0}")]
-    NetworkError(#[from] reqwest::Error),
+    NetworkError(reqwest::Error),
+    #[error("Request timed out: {0}")]
+    Timeout(String),
     #[error("Path not found: {0}")]
     PathNotFound(String),
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
-    #[error("GitHub rate limit exceeded")]
-    RateLimitExceeded,
-    #[error("GitHub repository not found")]
+    #[error("Rate limit exceeded{}", .0.map(|secs| format!(" (resets in {}s)", secs)).unwrap_or_default())]
+    RateLimitExceeded(Option<u64>),
+    #[error("Repository not found")]
     RepoNotFound,
     #[error("File is not valid UTF-8 text: {0}")]
     NotTextFile(String),
+    #[error("File looks binary: {0}")]
+    BinaryContent(String),
+    #[error("Path is not valid UTF-8: {0}")]
+    NonUtf8Path(String),
+    #[error("Watch error: {0}")]
+    WatchError(#[from] notify::Error),
+    #[error("Could not convert {0}: {1}")]
+    ConversionFailed(String, String),
+    #[error("Token count failed: {0}")]
+    TokenCountFailed(String),
+}
+
+impl From<reqwest::Error> for TextSourceError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            TextSourceError::Timeout(
+                e.url().map(|u| u.to_string()).unwrap_or_default(),
+            )
+        } else {
+            TextSourceError::NetworkError(e)
+        }
+    }
 }
 
 #[async_trait]
@@ -36,13 +73,102 @@ pub trait TextSource: Send + Sync {
         &self,
         filter: &FilterConfig,
     ) -> Result<Vec<SourceFile>, TextSourceError>;
-    async fn get_file_content(&self, source_file: &SourceFile) -> Result<String, TextSourceError>;
+    async fn get_file_bytes(&self, source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError>;
+    async fn get_file_content(
+        &self,
+        source_file: &SourceFile,
+        filter: &FilterConfig,
+    ) -> Result<String, TextSourceError> {
+        let bytes = self.get_file_bytes(source_file).await?;
+        filter.decode_text_async(&source_file.path, bytes).await
+    }
+    async fn get_file_contents(
+        &self,
+        source_files: &[SourceFile],
+        filter: &FilterConfig,
+    ) -> Vec<Result<String, TextSourceError>> {
+        let mut contents = Vec::with_capacity(source_files.len());
+        for source_file in source_files {
+            contents.push(self.get_file_content(source_file, filter).await);
+        }
+        contents
+    }
+    fn web_url(&self, _source_file: &SourceFile) -> Option<String> {
+        None
+    }
+    fn ignored_paths(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    fn submodules(&self) -> Vec<Submodule> {
+        Vec::new()
+    }
+    fn index_warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn watch(
+        &self,
+        _tx: mpsc::UnboundedSender<PathBuf>,
+    ) -> Result<Option<RecommendedWatcher>, TextSourceError> {
+        Ok(None)
+    }
+    fn affects_index(&self, _path: &Path, _filter: &FilterConfig) -> bool {
+        true
+    }
+}
+
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+pub(crate) async fn fetch_all<T, F>(jobs: Vec<F>, concurrency: usize) -> Vec<Result<T, TextSourceError>>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T, TextSourceError>> + Send + 'static,
+{
+    let mut results: Vec<Option<Result<T, TextSourceError>>> =
+        jobs.iter().map(|_| None).collect();
+    let mut set = JoinSet::new();
+    for (i, job) in jobs.into_iter().enumerate() {
+        if set.len() >= concurrency.max(1) {
+            if let Some(Ok((j, result))) = set.join_next().await {
+                results[j] = Some(result);
+            }
+        }
+        set.spawn(async move { (i, job.await) });
+    }
+    while let Some(joined) = set.join_next().await {
+        if let Ok((j, result)) = joined {
+            results[j] = Some(result);
+        }
+    }
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|| Err(std::io::Error::other("fetch task failed").into())))
+        .collect()
 }
 
 #[derive(Debug, Clone)]
 pub struct FilterConfig {
     pub additional_text_extensions: HashSet<String>,
     pub additional_binary_extensions: HashSet<String>,
+    pub allowlist_extensions: Option<HashSet<String>>,
+    pub allowlist_no_extension: bool,
+    pub binary_extensions: HashSet<String>,
+    pub converters: HashMap<String, String>,
+    pub file_list: Option<Vec<String>>,
+    pub changed_only: Option<ChangeScope>,
+    pub ignore_case: bool,
+    pub skip_empty: bool,
+    pub respect_gitignore: bool,
+    pub no_ext_filter: bool,
+    pub concurrency: usize,
+    pub modified_within: Option<std::time::Duration>,
+    pub include_submodules: bool,
+    pub exclude_file: Option<std::path::PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ChangeScope {
+    LastCommits(usize),
+    SinceRef(String),
 }
 
 impl Default for FilterConfig {
@@ -50,6 +176,20 @@ impl Default for FilterConfig {
         Self {
             additional_text_extensions: HashSet::new(),
             additional_binary_extensions: HashSet::new(),
+            allowlist_extensions: None,
+            allowlist_no_extension: true,
+            binary_extensions: DEFAULT_BINARY_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            converters: HashMap::new(),
+            file_list: None,
+            changed_only: None,
+            ignore_case: false,
+            skip_empty: false,
+            respect_gitignore: true,
+            no_ext_filter: false,
+            concurrency: DEFAULT_CONCURRENCY,
+            modified_within: None,
+            include_submodules: false,
+            exclude_file: None,
         }
     }
 }
@@ -59,72 +199,335 @@ impl FilterConfig {
         Self::default()
     }
     pub fn is_text_extension(&self, ext: String) -> bool {
+        if let Some(allowlist) = &self.allowlist_extensions {
+            return allowlist.contains(&ext);
+        }
         if self.additional_binary_extensions.contains(&ext) {
             return false;
         }
-        if self.additional_text_extensions.contains(&ext) {
+        if self.additional_text_extensions.contains(&ext) || self.converters.contains_key(&ext) {
             return true;
         }
-        !NON_TEXT_EXTENSIONS.contains(ext.as_str())
+        self.no_ext_filter || !self.binary_extensions.contains(&ext)
+    }
+    pub fn needs_content_check(&self, path: &str) -> bool {
+        if !self.no_ext_filter || self.converter_for(path).is_some() {
+            return false;
+        }
+        match file_system::get_extension(path) {
+            Some(ext) => {
+                !self.additional_text_extensions.contains(&ext) && self.binary_extensions.contains(&ext)
+            }
+            None => true,
+        }
+    }
+    pub fn is_binary_extension(&self, ext: &str) -> bool {
+        self.additional_binary_extensions.contains(ext)
+            || (!self.additional_text_extensions.contains(ext) && self.binary_extensions.contains(ext))
+    }
+    pub fn set_binary_extension(&mut self, ext: &str, binary: bool) {
+        if binary {
+            self.additional_text_extensions.remove(ext);
+            self.binary_extensions.insert(ext.to_string());
+        } else {
+            self.additional_binary_extensions.remove(ext);
+            self.binary_extensions.remove(ext);
+        }
+    }
+    pub fn is_allowlist_mode(&self) -> bool {
+        self.allowlist_extensions.is_some()
+    }
+    pub fn allows_no_extension(&self) -> bool {
+        !self.is_allowlist_mode() || self.allowlist_no_extension
+    }
+    pub fn normalize_name(&self, name: &str) -> String {
+        if self.ignore_case {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+    pub fn converter_for(&self, path: &str) -> Option<&str> {
+        let ext = file_system::get_extension(path)?;
+        self.converters.get(&ext).map(|c| c.as_str())
+    }
+    pub fn decode_text(&self, path: &str, bytes: Vec<u8>) -> Result<String, TextSourceError> {
+        if let Some(command) = self.converter_for(path) {
+            return convert::convert_bytes(command, &bytes, path);
+        }
+        if self.needs_content_check(path) && looks_binary(&bytes) {
+            return Err(TextSourceError::BinaryContent(path.to_string()));
+        }
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(text),
+            Err(e) if self.forces_text(path) => {
+                let bytes = e.into_bytes();
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Err(_) => Err(TextSourceError::NotTextFile(path.to_string())),
+        }
+    }
+    /// Like `decode_text`, but runs a configured converter on the blocking
+    /// pool so a slow tool doesn't hold up a runtime worker.
+    pub async fn decode_text_async(&self, path: &str, bytes: Vec<u8>) -> Result<String, TextSourceError> {
+        let Some(command) = self.converter_for(path) else {
+            return self.decode_text(path, bytes);
+        };
+        let (command, path) = (command.to_string(), path.to_string());
+        tokio::task::spawn_blocking(move || convert::convert_bytes(&command, &bytes, &path))
+            .await
+            .map_err(|e| TextSourceError::IoError(std::io::Error::other(e)))?
+    }
+    pub fn forces_text(&self, path: &str) -> bool {
+        file_system::get_extension(path).is_some_and(|ext| self.additional_text_extensions.contains(&ext))
+    }
+}
+
+pub const MODIFIED_WINDOWS: &[u64] = &[3600, 24 * 3600, 7 * 24 * 3600];
+
+pub fn parse_window(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    let (split, unit) = value.char_indices().last()?;
+    let n: u64 = value[..split].parse().ok().filter(|n| *n > 0)?;
+    let unit_secs = match unit {
+        'm' => 60,
+        'h' => 3600,
+        'd' => 24 * 3600,
+        'w' => 7 * 24 * 3600,
+        _ => return None,
+    };
+    Some(std::time::Duration::from_secs(n.checked_mul(unit_secs)?))
+}
+
+pub fn format_window(window: std::time::Duration) -> String {
+    let secs = window.as_secs();
+    if secs.is_multiple_of(24 * 3600) {
+        format!("{}d", secs / (24 * 3600))
+    } else if secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else {
+        format!("{}m", secs.div_ceil(60))
     }
 }
 
+const BINARY_SNIFF_LEN: usize = 8000;
+
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct SourceFile {
     pub path: String,
     pub source_type: SourceType,
+    pub revision: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submodule {
+    pub path: String,
+    pub root: String,
+    pub repository: Option<String>,
+    pub commit: String,
+    pub included: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SourceType {
     FileSystem {
         base_path: std::path::PathBuf,
+        relative_path: std::path::PathBuf,
     },
     GitHub {
         owner: String,
         repo: String,
         branch: String,
     },
+    Gist {
+        raw_url: String,
+    },
+    Bitbucket {
+        workspace: String,
+        repo: String,
+        commit: String,
+    },
+    Stdin,
 }
 
-static NON_TEXT_EXTENSIONS: Lazy<HashSet<&str>> = Lazy::new(|| {
-    let mut set = HashSet::new();
-    set.extend([
-        "exe", "dll", "so", "dylib", "bin", "app", "msi", "sys", "com", "o", "obj", "class",
-    ]);
-    set.extend([
-        "zip", "rar", "7z", "tar", "gz", "bz2", "xz", "iso", "dmg", "img", "tgz",
-    ]);
-    set.extend([
-        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp", "ico", "svg", "eps", "raw", "cr2",
-        "nef", "heic",
-    ]);
-    set.extend([
-        "mp3", "wav", "ogg", "flac", "m4a", "wma", "aac", "mid", "midi", "aiff",
-    ]);
-    set.extend([
-        "mp4", "avi", "mkv", "mov", "wmv", "flv", "webm", "m4v", "mpg", "mpeg", "3gp",
-    ]);
-    set.extend([
-        "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "pages", "numbers", "key", "indd",
-        "psd", "ai",
-    ]);
-    set.extend(["db", "sqlite", "mdb", "accdb", "dbf", "dat", "mdf", "sdf"]);
-    set.extend(["ttf", "otf", "woff", "woff2", "eot"]);
-    set.extend([
-        "pyc", "pyo", "pyd", "jar", "war", "deb", "rpm", "lib", "a", "pak", "cache", "idx", "mo",
-        "gmo", "pdb",
-    ]);
-    set
-});
+pub const DEFAULT_BINARY_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "so", "dylib", "bin", "app", "msi", "sys", "com", "o", "obj", "class",
+    "zip", "rar", "7z", "tar", "gz", "bz2", "xz", "iso", "dmg", "img", "tgz",
+    "jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp", "ico", "svg", "eps", "raw", "cr2",
+    "nef", "heic",
+    "mp3", "wav", "ogg", "flac", "m4a", "wma", "aac", "mid", "midi", "aiff",
+    "mp4", "avi", "mkv", "mov", "wmv", "flv", "webm", "m4v", "mpg", "mpeg", "3gp",
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "pages", "numbers", "key", "indd",
+    "psd", "ai",
+    "db", "sqlite", "mdb", "accdb", "dbf", "dat", "mdf", "sdf",
+    "ttf", "otf", "woff", "woff2", "eot",
+    "pyc", "pyo", "pyd", "jar", "war", "deb", "rpm", "lib", "a", "pak", "cache", "idx", "mo",
+    "gmo", "pdb",
+];
 
 pub async fn create_text_source(source: &str) -> Result<Box<dyn TextSource>, TextSourceError> {
-    if source.starts_with("https://github.com") {
-        let (owner, repo, branch, subpath) = github::GitHubSource::parse_github_url(source)?;
+    let parts = split_sources(source);
+    if parts.len() < 2 {
+        return create_single_source(source).await;
+    }
+    let mut sources = Vec::new();
+    for part in parts {
+        sources.push((crate::output::file::source_name(part), create_single_source(part).await?));
+    }
+    Ok(Box::new(multi::MultiSource::new(sources)))
+}
+
+pub fn join_url_path(base: &str, path: &str) -> String {
+    let Ok(mut url) = url::Url::parse(base) else {
+        return format!("{}/{}", base, path);
+    };
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().extend(path.split('/'));
+    }
+    url.to_string()
+}
+
+pub fn split_sources(source: &str) -> Vec<&str> {
+    source
+        .split('\n')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .flat_map(split_on_commas)
+        .collect()
+}
+
+fn split_on_commas(line: &str) -> Vec<&str> {
+    if !line.contains(',') || std::path::Path::new(&normalize_source(line)).exists() {
+        return vec![line];
+    }
+    let parts: Vec<&str> = line.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+    if parts.iter().all(|part| is_source(part)) {
+        parts
+    } else {
+        vec![line]
+    }
+}
+
+fn is_source(part: &str) -> bool {
+    let normalized = normalize_source(part);
+    normalized == stdin::STDIN_SOURCE
+        || normalized.starts_with("https://")
+        || normalized.starts_with("http://")
+        || std::path::Path::new(&normalized).exists()
+}
+
+pub fn normalize_source(source: &str) -> String {
+    let mut trimmed = source.trim();
+    for quote in ['"', '\''] {
+        if trimmed.len() >= 2 && trimmed.starts_with(quote) && trimmed.ends_with(quote) {
+            trimmed = trimmed[1..trimmed.len() - 1].trim();
+        }
+    }
+    if trimmed.starts_with("https://") || trimmed.starts_with("http://") {
+        return trimmed.to_string();
+    }
+    if trimmed.starts_with("file://") {
+        if let Some(path) = url::Url::parse(trimmed).ok().and_then(|u| u.to_file_path().ok()) {
+            return path.to_string_lossy().to_string();
+        }
+        trimmed = &trimmed["file://".len()..];
+    }
+    let expanded = crate::config::expand_path(trimmed);
+    let without_slash = expanded.trim_end_matches(['/', '\\']);
+    if without_slash.is_empty() || without_slash.ends_with(':') {
+        expanded
+    } else {
+        without_slash.to_string()
+    }
+}
+
+pub fn is_repository_source(source: &str) -> bool {
+    let github_config = github::GitHubConfig::from_env();
+    let bitbucket_config = bitbucket::BitbucketConfig::from_env();
+    split_sources(source).into_iter().map(normalize_source).any(|part| {
+        part.starts_with(&bitbucket_config.web_prefix())
+            || (part.starts_with(&github_config.web_prefix()) && !part.starts_with(&github_config.gist_prefix()))
+    })
+}
+
+async fn create_single_source(source: &str) -> Result<Box<dyn TextSource>, TextSourceError> {
+    let source = normalize_source(source);
+    let source = source.as_str();
+    if source == stdin::STDIN_SOURCE {
+        return Ok(Box::new(stdin::StdinSource));
+    }
+    let github_config = github::GitHubConfig::from_env();
+    let bitbucket_config = bitbucket::BitbucketConfig::from_env();
+    if source.starts_with(&bitbucket_config.web_prefix()) {
+        let (workspace, repo, branch, subpath) =
+            bitbucket::BitbucketSource::parse_bitbucket_url(source, &bitbucket_config.host)?;
+        Ok(Box::new(bitbucket::BitbucketSource::new(
+            workspace, repo, branch, subpath, bitbucket_config,
+        )))
+    } else if source.starts_with(&github_config.gist_prefix()) {
+        let id = gist::GistSource::parse_gist_url(source, &github_config.gist_prefix())?;
+        Ok(Box::new(gist::GistSource::new(id, github_config)))
+    } else if source.starts_with(&github_config.web_prefix()) {
+        let (owner, repo, branch, subpath) =
+            github::GitHubSource::parse_github_url(source, &github_config.host)?;
         Ok(Box::new(github::GitHubSource::new(
-            owner, repo, branch, subpath,
+            owner, repo, branch, subpath, github_config,
         )))
     } else {
         Ok(Box::new(file_system::FileSystemSource::new(source)?))
     }
-}
\ No newline at end of file
+}
+
+pub fn read_file_list(path: &std::path::Path) -> Result<Vec<String>, TextSourceError> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| l.replace('\\', "/").trim_start_matches("./").to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{join_url_path, split_sources};
+
+    #[test]
+    fn encodes_each_path_segment() {
+        assert_eq!(
+            join_url_path("https://github.com/o/r/blob/main", "docs/C# notes/100%?.md"),
+            "https://github.com/o/r/blob/main/docs/C%23%20notes/100%25%3F.md"
+        );
+        assert_eq!(
+            join_url_path("https://github.com/o/r/blob/feature/x", "src/lib.rs"),
+            "https://github.com/o/r/blob/feature/x/src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn splits_urls_and_existing_paths_on_commas() {
+        assert_eq!(
+            split_sources("https://github.com/a/b, https://github.com/c/d"),
+            ["https://github.com/a/b", "https://github.com/c/d"]
+        );
+        let dir = std::env::temp_dir().join(format!("anvil-sources-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("one")).unwrap();
+        std::fs::create_dir_all(dir.join("two,three")).unwrap();
+        let one = dir.join("one").to_string_lossy().into_owned();
+        let comma = dir.join("two,three").to_string_lossy().into_owned();
+        assert_eq!(split_sources(&format!("{},https://github.com/a/b", one)), [one.as_str(), "https://github.com/a/b"]);
+        assert_eq!(split_sources(&comma), [comma.as_str()]);
+        assert_eq!(split_sources(&format!("{}\n{}", comma, one)), [comma.as_str(), one.as_str()]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keeps_paths_with_commas_that_do_not_split_into_sources() {
+        assert_eq!(split_sources("/no/such/dir, really"), ["/no/such/dir, really"]);
+        assert_eq!(split_sources("  \n\n"), Vec::<&str>::new());
+    }
+}
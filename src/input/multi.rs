@@ -0,0 +1,135 @@
+use super::{FilterConfig, SourceFile, Submodule, TextSource, TextSourceError};
+use async_trait::async_trait;
+
+pub struct MultiSource {
+    pub sources: Vec<(String, Box<dyn TextSource>)>,
+}
+
+impl MultiSource {
+    pub fn new(sources: Vec<(String, Box<dyn TextSource>)>) -> Self {
+        let mut labelled: Vec<(String, Box<dyn TextSource>)> = Vec::new();
+        for (label, source) in sources {
+            let mut unique = label.clone();
+            let mut n = 2;
+            while labelled.iter().any(|(l, _)| *l == unique) {
+                unique = format!("{}-{}", label, n);
+                n += 1;
+            }
+            labelled.push((unique, source));
+        }
+        Self { sources: labelled }
+    }
+    fn inner_file(&self, source_file: &SourceFile) -> Option<(usize, SourceFile)> {
+        self.sources.iter().enumerate().find_map(|(index, (label, _))| {
+            let path = source_file.path.strip_prefix(label.as_str())?.strip_prefix('/')?;
+            Some((
+                index,
+                SourceFile {
+                    path: path.to_string(),
+                    ..source_file.clone()
+                },
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl TextSource for MultiSource {
+    async fn get_file_index(
+        &self,
+        filter: &FilterConfig,
+    ) -> Result<Vec<SourceFile>, TextSourceError> {
+        let mut files = Vec::new();
+        for (label, source) in &self.sources {
+            let mut source_filter = filter.clone();
+            if let Some(list) = &filter.file_list {
+                let prefix = format!("{}/", label);
+                source_filter.file_list = Some(
+                    list.iter()
+                        .filter_map(|p| p.strip_prefix(prefix.as_str()).map(|p| p.to_string()))
+                        .collect(),
+                );
+            }
+            for mut file in source.get_file_index(&source_filter).await? {
+                file.path = format!("{}/{}", label, file.path);
+                files.push(file);
+            }
+        }
+        Ok(files)
+    }
+    fn web_url(&self, source_file: &SourceFile) -> Option<String> {
+        let (index, inner) = self.inner_file(source_file)?;
+        self.sources[index].1.web_url(&inner)
+    }
+    fn ignored_paths(&self) -> Vec<(String, String)> {
+        self.sources
+            .iter()
+            .flat_map(|(label, source)| {
+                source
+                    .ignored_paths()
+                    .into_iter()
+                    .map(move |(path, pattern)| (format!("{}/{}", label, path), pattern))
+            })
+            .collect()
+    }
+    fn index_warnings(&self) -> Vec<String> {
+        self.sources
+            .iter()
+            .flat_map(|(label, source)| {
+                source
+                    .index_warnings()
+                    .into_iter()
+                    .map(move |warning| format!("{}: {}", label, warning))
+            })
+            .collect()
+    }
+    fn submodules(&self) -> Vec<Submodule> {
+        self.sources
+            .iter()
+            .flat_map(|(label, source)| {
+                source.submodules().into_iter().map(move |submodule| Submodule {
+                    path: format!("{}/{}", label, submodule.path),
+                    ..submodule
+                })
+            })
+            .collect()
+    }
+    async fn get_file_bytes(&self, source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError> {
+        let (index, inner) = self
+            .inner_file(source_file)
+            .ok_or_else(|| TextSourceError::PathNotFound(source_file.path.clone()))?;
+        self.sources[index].1.get_file_bytes(&inner).await
+    }
+    async fn get_file_contents(
+        &self,
+        source_files: &[SourceFile],
+        filter: &FilterConfig,
+    ) -> Vec<Result<String, TextSourceError>> {
+        let mut results: Vec<Option<Result<String, TextSourceError>>> =
+            source_files.iter().map(|_| None).collect();
+        for (index, (_, source)) in self.sources.iter().enumerate() {
+            let mut positions = Vec::new();
+            let mut inner_files = Vec::new();
+            for (i, sf) in source_files.iter().enumerate() {
+                if let Some((source_index, inner)) = self.inner_file(sf) {
+                    if source_index == index {
+                        positions.push(i);
+                        inner_files.push(inner);
+                    }
+                }
+            }
+            if inner_files.is_empty() {
+                continue;
+            }
+            let contents = source.get_file_contents(&inner_files, filter).await;
+            for (i, content) in positions.into_iter().zip(contents) {
+                results[i] = Some(content);
+            }
+        }
+        results
+            .into_iter()
+            .zip(source_files)
+            .map(|(r, sf)| r.unwrap_or_else(|| Err(TextSourceError::PathNotFound(sf.path.clone()))))
+            .collect()
+    }
+}
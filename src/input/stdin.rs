@@ -0,0 +1,43 @@
+use super::{FilterConfig, SourceFile, SourceType, TextSource, TextSourceError};
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use std::io::Read;
+
+pub const STDIN_SOURCE: &str = "-";
+const STDIN_FILE_NAME: &str = "stdin.txt";
+
+static STDIN_CONTENT: OnceCell<Vec<u8>> = OnceCell::new();
+
+pub fn read_stdin() -> Result<usize, std::io::Error> {
+    let mut content = Vec::new();
+    std::io::stdin().read_to_end(&mut content)?;
+    let len = content.len();
+    let _ = STDIN_CONTENT.set(content);
+    Ok(len)
+}
+
+pub struct StdinSource;
+
+#[async_trait]
+impl TextSource for StdinSource {
+    async fn get_file_index(
+        &self,
+        filter: &FilterConfig,
+    ) -> Result<Vec<SourceFile>, TextSourceError> {
+        let content = STDIN_CONTENT.get().ok_or(TextSourceError::InvalidSource)?;
+        if filter.skip_empty && content.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![SourceFile {
+            path: STDIN_FILE_NAME.to_string(),
+            source_type: SourceType::Stdin,
+            revision: Some(content.len().to_string()),
+        }])
+    }
+    async fn get_file_bytes(&self, _source_file: &SourceFile) -> Result<Vec<u8>, TextSourceError> {
+        STDIN_CONTENT
+            .get()
+            .cloned()
+            .ok_or(TextSourceError::InvalidSource)
+    }
+}
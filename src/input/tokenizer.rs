@@ -1,10 +1,42 @@
 use log::info;
-use tiktoken_rs::o200k_base;
+use tiktoken_rs::{cl100k_base, o200k_base};
 
-pub fn count_tokens_in_content(content: &str) -> Result<usize, String> {
-	info!("Starting token count for content of length {}", content.len());
-	let bpe = o200k_base().map_err(|e| e.to_string())?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenizerModel {
+	O200kBase,
+	Cl100kBase,
+}
+
+impl TokenizerModel {
+	pub fn name(&self) -> &'static str {
+		match self {
+			TokenizerModel::O200kBase => "o200k_base",
+			TokenizerModel::Cl100kBase => "cl100k_base",
+		}
+	}
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"o200k_base" => Some(TokenizerModel::O200kBase),
+			"cl100k_base" => Some(TokenizerModel::Cl100kBase),
+			_ => None,
+		}
+	}
+	pub fn next(&self) -> Self {
+		match self {
+			TokenizerModel::O200kBase => TokenizerModel::Cl100kBase,
+			TokenizerModel::Cl100kBase => TokenizerModel::O200kBase,
+		}
+	}
+}
+
+pub fn count_tokens_in_content(content: &str, model: TokenizerModel) -> Result<usize, String> {
+	info!("Starting {} token count for content of length {}", model.name(), content.len());
+	let bpe = match model {
+		TokenizerModel::O200kBase => o200k_base(),
+		TokenizerModel::Cl100kBase => cl100k_base(),
+	}
+	.map_err(|e| e.to_string())?;
 	let tokens = bpe.encode_with_special_tokens(content);
 	info!("Token counting complete, total tokens: {}", tokens.len());
 	Ok(tokens.len())
-}
\ No newline at end of file
+}
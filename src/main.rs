@@ -1,5 +1,6 @@
 use std::env;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
@@ -9,8 +10,15 @@ use crossterm::{
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use tokio::runtime::Runtime;
+use crate::config::{config_dir, env_value};
+use crate::input::convert::{load_converters, parse_converter, program_available};
+use crate::input::read_file_list;
+use crate::ui::confirm::{confirm_skipped, set_confirm_skipped};
+use crate::ui::spinner::Spinner;
 use crate::ui::App;
 
+mod cli;
+mod config;
 mod ui;
 mod input;
 mod output;
@@ -20,31 +28,187 @@ fn set_window_title(title: &str) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn open_in_editor(path: &Path) -> Result<(), String> {
+    let editor = env::var("VISUAL")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .or_else(|| env::var("EDITOR").ok().filter(|v| !v.trim().is_empty()))
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Could not start {}: {}", program, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} exited with {}", program, status))
+    }
+}
+
+const DEFAULT_LOG_FILE: &str = "ai-anvil-tui.log";
+
+fn default_log_file() -> Result<PathBuf, String> {
+    let dir = config_dir().ok_or_else(|| "No config directory to log to; pass --log-file <PATH>".to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    Ok(dir.join(DEFAULT_LOG_FILE))
+}
+
+fn init_logger(verbosity: u8, log_file: Option<&str>) -> Result<Option<PathBuf>, String> {
+    let level = match verbosity {
+        0 => log::LevelFilter::Off,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level).parse_default_env().format_timestamp_millis();
+    let logging = verbosity > 0 || env_value("RUST_LOG").is_some();
+    let path = match log_file {
+        Some(path) => Some(PathBuf::from(path)),
+        None if logging && std::io::stderr().is_terminal() => Some(default_log_file()?),
+        None => None,
+    };
+    if let Some(path) = &path {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("Could not create log file {}: {}", path.display(), e))?;
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+    builder.init();
+    Ok(path.filter(|_| logging))
+}
+
 fn main() {
-    env_logger::init();
+    let cli = match cli::parse_args(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Try '--help' for more information.");
+            std::process::exit(2);
+        }
+    };
+    let log_path = match init_logger(cli.verbosity, cli.log_file.as_deref()) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if cli.show_help {
+        println!("{}", cli::usage());
+        return;
+    }
+    if cli.show_version {
+        println!("{}", cli::version());
+        return;
+    }
 
     if let Err(e) = set_window_title("AI Anvil") {
         log::error!("Could not set title: {}", e);
     }
 
+    let file_list = match &cli.from_list {
+        Some(list_path) => match read_file_list(Path::new(list_path)) {
+            Ok(list) => Some(list),
+            Err(e) => {
+                eprintln!("Could not read file list {}: {}", list_path, e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    if cli.source.as_deref() == Some(input::stdin::STDIN_SOURCE) {
+        if let Err(e) = input::stdin::read_stdin() {
+            eprintln!("Could not read stdin: {}", e);
+            std::process::exit(2);
+        }
+    }
+
     let rt = Runtime::new().unwrap();
-    let mut args = env::args();
-    let _ = args.next();
-    let default_path = args.next().unwrap_or_else(|| {
+    let default_path = cli.source.unwrap_or_else(|| {
         std::env::current_dir()
             .unwrap_or_else(|_| PathBuf::from("."))
             .to_string_lossy()
             .to_string()
     });
-    let default_output_path = format!(
-        "{}{}merged_context.txt",
-        std::env::current_dir()
-            .unwrap_or_else(|_| PathBuf::from("." ))
-            .to_string_lossy(),
-        std::path::MAIN_SEPARATOR
-    );
     rt.block_on(async {
-        let mut app = App::new(default_path, default_output_path);
+        let mut app = App::new(default_path);
+        app.output_template = cli.output_template;
+        app.reset_output_path();
+        app.filter_config.file_list = file_list;
+        app.filter_config.changed_only = cli.changed_only;
+        app.filter_config.ignore_case = cli.ignore_case;
+        app.filter_config.skip_empty = cli.skip_empty;
+        app.filter_config.no_ext_filter = cli.no_ext_filter;
+        app.filter_config.modified_within = cli.modified_within;
+        app.filter_config.include_submodules = cli.include_submodules;
+        app.set_concurrency(cli.concurrency);
+        let mut converters = load_converters();
+        for spec in &cli.converters {
+            match parse_converter(spec) {
+                Some((ext, command)) => {
+                    converters.insert(ext, command);
+                }
+                None => {
+                    eprintln!("Invalid --convert value: {} (expected EXT=COMMAND)", spec);
+                    std::process::exit(2);
+                }
+            }
+        }
+        let mut missing: Vec<String> = Vec::new();
+        converters.retain(|ext, command| {
+            let available = program_available(command);
+            if !available {
+                log::warn!("Converter for .{} not found: {}", ext, command);
+                missing.push(format!(".{}", ext));
+            }
+            available
+        });
+        if !missing.is_empty() {
+            missing.sort();
+            app.status_message = Some(format!("Converter not found for {}  •  those files are skipped", missing.join(", ")));
+        }
+        app.filter_config.converters = converters;
+        if let Some(name) = &cli.price_model {
+            match app.pricing.iter().find(|p| p.name.eq_ignore_ascii_case(name)) {
+                Some(price) => app.source_files_panel.price = Some(price.clone()),
+                None => {
+                    let names: Vec<&str> = app.pricing.iter().map(|p| p.name.as_str()).collect();
+                    eprintln!("Unknown price model: {} (known: {})", name, names.join(", "));
+                    std::process::exit(2);
+                }
+            }
+        }
+        app.tokenizer = cli.tokenizer;
+        app.split = cli.split;
+        app.max_file_tokens = cli.max_file_tokens;
+        if let Some(prefix) = cli.path_prefix {
+            app.path_prefix = Some(prefix);
+            app.output_panel.prefix_paths = true;
+        }
+        if let Some(style) = cli.tree_style {
+            app.output_panel.tree_header = true;
+            app.output_panel.tree_style = style;
+        }
+        app.output_panel.table_of_contents = cli.table_of_contents;
+        app.output_panel.redact_secrets = cli.redact_secrets;
+        app.output_panel.all_or_nothing = cli.all_or_nothing;
+        if let Some(column) = cli.wrap_column {
+            app.output_panel.wrap_lines = true;
+            app.output_panel.wrap_column = column;
+        }
+        if cli.confirm {
+            if let Err(e) = set_confirm_skipped(false) {
+                app.status_message = Some(e);
+            }
+        }
+        app.confirm_merges = !cli.no_confirm && env_value("ANVIL_NO_CONFIRM").is_none() && !confirm_skipped();
+        app.post_process = cli.post_process.or_else(|| env_value("ANVIL_POST_PROCESS"));
+        app.set_preamble(cli.preamble, cli.postamble);
+        app.max_output_bytes = (cli.max_output_mb > 0).then(|| cli.max_output_mb * 1024 * 1024);
         app.reload_files_needed = true;
 
         enable_raw_mode().unwrap();
@@ -54,18 +218,38 @@ fn main() {
         let mut terminal = Terminal::new(backend).unwrap();
 
         loop {
+            app.process_fs_changes();
             if app.reload_files_needed && !app.processing {
                 app.processing = true;
+                app.begin_progress();
                 terminal.draw(|f| app.draw(f)).unwrap();
+                let spinner = Spinner::start(app.spinner_position(terminal.get_frame().area()));
                 app.reload_files_immediate().await;
+                spinner.stop();
+                app.processing = false;
+                app.progress = None;
+            }
+
+            if app.pending_search.is_some() && !app.processing {
+                app.processing = true;
+                app.begin_progress();
+                terminal.draw(|f| app.draw(f)).unwrap();
+                let spinner = Spinner::start(app.spinner_position(terminal.get_frame().area()));
+                app.search_immediate().await;
+                spinner.stop();
                 app.processing = false;
+                app.progress = None;
             }
 
             if app.merge_needed && !app.processing {
                 app.processing = true;
+                app.begin_progress();
                 terminal.draw(|f| app.draw(f)).unwrap();
+                let spinner = Spinner::start(app.spinner_position(terminal.get_frame().area()));
                 app.merge_immediate().await;
+                spinner.stop();
                 app.processing = false;
+                app.progress = None;
             }
 
             terminal.draw(|f| app.draw(f)).unwrap();
@@ -77,6 +261,20 @@ fn main() {
                 match event::read().unwrap() {
                     Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
                         app.update(key_event).await;
+                        if let Some(path) = app.pending_edit.take() {
+                            disable_raw_mode().unwrap();
+                            execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture).unwrap();
+                            terminal.show_cursor().unwrap();
+                            let result = open_in_editor(&path);
+                            enable_raw_mode().unwrap();
+                            execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture).unwrap();
+                            terminal.clear().unwrap();
+                            app.finish_edit(result);
+                        }
+                    }
+                    Event::Resize(width, height) => {
+                        app.resize(width, height);
+                        terminal.autoresize().unwrap();
                     }
                     _ => {}
                 }
@@ -90,5 +288,8 @@ fn main() {
             DisableMouseCapture
         ).unwrap();
         terminal.show_cursor().unwrap();
+        if let Some(path) = log_path {
+            eprintln!("Log written to {}", path.display());
+        }
     });
 }
\ No newline at end of file
@@ -1,11 +1,48 @@
 use arboard::Clipboard;
+use std::thread;
+use std::time::Duration;
+
+const SET_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(150);
+const LARGE_CONTENT_BYTES: usize = 4 * 1024 * 1024;
 
 pub fn copy_clipboard(content: String) -> Result<(), String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard.set_text(content).map_err(|e| e.to_string())
+    let mut clipboard = open_clipboard()?;
+    let mut last_error = None;
+    for attempt in 0..SET_ATTEMPTS {
+        if attempt > 0 {
+            thread::sleep(RETRY_DELAY);
+            // Some backends (notably Windows) leave the handle unusable after a failed set.
+            clipboard = open_clipboard()?;
+        }
+        match clipboard.set_text(content.as_str()) {
+            Ok(()) => return Ok(()),
+            Err(arboard::Error::ClipboardNotSupported) => {
+                return Err("No clipboard available on this system".to_string());
+            }
+            Err(e) => {
+                log::warn!("Clipboard set attempt {} failed: {}", attempt + 1, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    let e = last_error.map(|e| e.to_string()).unwrap_or_default();
+    if content.len() >= LARGE_CONTENT_BYTES {
+        Err(format!(
+            "Clipboard rejected {} MB of text, it may be too large: {}",
+            content.len() / (1024 * 1024),
+            e
+        ))
+    } else {
+        Err(format!("Could not copy to clipboard: {}", e))
+    }
 }
 
 pub fn get_clipboard_content() -> Result<String, String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    let mut clipboard = open_clipboard()?;
     clipboard.get_text().map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+fn open_clipboard() -> Result<Clipboard, String> {
+    Clipboard::new().map_err(|e| format!("No clipboard available: {}", e))
+}
@@ -1,9 +1,312 @@
+use crate::config::expand_path;
 use std::fs;
 use std::io::Write;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn write_file(path: &str, content: &str) -> Result<(), String> {
+    let path = expand_path(path);
+    let path = path.as_str();
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Error creating directory: {}", e))?;
+        }
+    }
     let mut file = fs::File::create(path).map_err(|e| format!("Error creating file: {}", e))?;
     file.write_all(content.as_bytes())
         .map_err(|e| format!("Error writing file: {}", e))?;
     Ok(())
 }
+
+static STAGE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub struct StagedFile {
+    temp: PathBuf,
+    path: PathBuf,
+    backup: Option<PathBuf>,
+    committed: bool,
+}
+
+fn create_sibling(path: &Path, suffix: &str) -> Result<(PathBuf, fs::File), String> {
+    loop {
+        let candidate = PathBuf::from(format!(
+            "{}.{}-{}.{}",
+            path.display(),
+            std::process::id(),
+            STAGE_COUNTER.fetch_add(1, Ordering::Relaxed),
+            suffix
+        ));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&candidate) {
+            Ok(file) => return Ok((candidate, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Error creating file: {}", e)),
+        }
+    }
+}
+
+pub fn stage_file(path: &str, content: &str) -> Result<StagedFile, String> {
+    let path = PathBuf::from(expand_path(path));
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Error creating directory: {}", e))?;
+        }
+    }
+    let (temp, mut file) = create_sibling(&path, "partial")?;
+    if let Err(e) = file.write_all(content.as_bytes()) {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("Error writing file: {}", e));
+    }
+    Ok(StagedFile {
+        temp,
+        path,
+        backup: None,
+        committed: false,
+    })
+}
+
+fn commit_one(staged: &mut StagedFile) -> Result<(), String> {
+    if staged.path.exists() {
+        let (backup, _) = create_sibling(&staged.path, "bak")?;
+        fs::rename(&staged.path, &backup).map_err(|e| format!("Error moving old file aside: {}", e))?;
+        staged.backup = Some(backup);
+    }
+    fs::rename(&staged.temp, &staged.path).map_err(|e| format!("Error moving file into place: {}", e))?;
+    staged.committed = true;
+    Ok(())
+}
+
+pub fn commit_staged(staged: &mut [StagedFile]) -> Result<(), String> {
+    for index in 0..staged.len() {
+        if let Err(e) = commit_one(&mut staged[index]) {
+            rollback_staged(staged);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+pub fn rollback_staged(staged: &mut [StagedFile]) {
+    for file in staged.iter_mut() {
+        if file.committed {
+            let _ = fs::remove_file(&file.path);
+            file.committed = false;
+        } else {
+            let _ = fs::remove_file(&file.temp);
+        }
+        if let Some(backup) = file.backup.take() {
+            let _ = fs::rename(&backup, &file.path);
+        }
+    }
+}
+
+pub fn finish_staged(staged: &[StagedFile]) {
+    for file in staged {
+        if let Some(backup) = &file.backup {
+            let _ = fs::remove_file(backup);
+        }
+    }
+}
+
+pub fn validate_output_path(path: &str, default_extension: &str) -> Result<String, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("Output file path is empty".to_string());
+    }
+    if trimmed.ends_with('/') || trimmed.ends_with(MAIN_SEPARATOR) {
+        return Err(format!("Output path has no file name: {}", trimmed));
+    }
+    let mut p = PathBuf::from(trimmed);
+    let expanded = PathBuf::from(expand_path(trimmed));
+    if expanded.is_dir() {
+        return Err(format!("Output path is a directory: {}", trimmed));
+    }
+    if p.extension().is_none() {
+        p.set_extension(default_extension);
+    }
+    if let Some(parent) = expanded.parent() {
+        if parent.exists() && !parent.is_dir() {
+            return Err(format!(
+                "Not a directory: {}",
+                parent.to_string_lossy()
+            ));
+        }
+    }
+    Ok(p.to_string_lossy().to_string())
+}
+
+pub fn validate_output_dir(path: &str) -> Result<String, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("Output directory path is empty".to_string());
+    }
+    let expanded = PathBuf::from(expand_path(trimmed));
+    if expanded.exists() && !expanded.is_dir() {
+        return Err(format!("Output path is a file: {}", trimmed));
+    }
+    Ok(trimmed.to_string())
+}
+
+pub fn write_file_in_dir(dir: &str, relative_path: &str, content: &str) -> Result<(), String> {
+    let mut path = PathBuf::from(dir.trim());
+    for part in relative_path.split('/') {
+        if part.is_empty() || part == "." || part == ".." || Path::new(part).is_absolute() {
+            return Err(format!("Refusing to write outside the output directory: {}", relative_path));
+        }
+        path.push(part);
+    }
+    write_file(&path.to_string_lossy(), content)
+}
+
+pub fn expand_output_template(template: &str, source: &str) -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    template
+        .replace("{date}", &format_date(secs))
+        .replace("{timestamp}", &secs.to_string())
+        .replace("{source_name}", &source_name(source))
+}
+
+pub fn source_name(source: &str) -> String {
+    let parts = crate::input::split_sources(source);
+    if parts.len() > 1 {
+        return parts.iter().map(|p| source_name(p)).collect::<Vec<_>>().join("_");
+    }
+    if source.trim() == crate::input::stdin::STDIN_SOURCE {
+        return "stdin".to_string();
+    }
+    let normalized = crate::input::normalize_source(source);
+    let trimmed = normalized.trim_end_matches(['/', '\\']);
+    let name = if trimmed.starts_with("https://") {
+        trimmed.split('/').nth(4).map(|s| s.trim_end_matches(".git").to_string())
+    } else {
+        let path = Path::new(trimmed);
+        path.canonicalize()
+            .unwrap_or_else(|_| path.to_path_buf())
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+    };
+    let sanitized: String = name
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() {
+        "source".to_string()
+    } else {
+        sanitized
+    }
+}
+
+pub fn default_path_prefix(source: &str) -> Option<String> {
+    if crate::input::split_sources(source).len() > 1 {
+        return None;
+    }
+    let trimmed = source.trim().trim_end_matches(['/', '\\']);
+    if trimmed.starts_with("https://") {
+        let segments: Vec<&str> = trimmed.split(['?', '#']).next()?.split('/').skip(3).collect();
+        return match segments.as_slice() {
+            [_, repo, "tree" | "src", _, subpath @ ..] if !subpath.is_empty() => Some(format!(
+                "{}/{}",
+                repo.trim_end_matches(".git"),
+                subpath.join("/")
+            )),
+            _ => Some(source_name(trimmed)),
+        };
+    }
+    Some(source_name(trimmed))
+}
+
+pub fn format_date(unix_secs: u64) -> String {
+    // Civil-from-days conversion (UTC), see http://howardhinnant.github.io/date_algorithms.html
+    let days = (unix_secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+pub fn selection_list_path(output_file: &str) -> String {
+    let p = Path::new(output_file.trim());
+    let stem = p
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "merged_context".to_string());
+    p.with_file_name(format!("{}.files.txt", stem))
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn part_path(output_file: &str, part: usize) -> String {
+    let p = Path::new(output_file.trim());
+    let stem = p
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "merged_context".to_string());
+    let name = match p.extension() {
+        Some(ext) => format!("{}_part{}.{}", stem, part, ext.to_string_lossy()),
+        None => format!("{}_part{}", stem, part),
+    };
+    p.with_file_name(name).to_string_lossy().to_string()
+}
+
+pub fn write_file_list(path: &str, paths: &[String]) -> Result<(), String> {
+    let mut content = paths.join("\n");
+    content.push('\n');
+    write_file(path, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("anvil-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rollback_restores_replaced_files_and_leaves_no_temps() {
+        let dir = scratch_dir("rollback");
+        let first = dir.join("merged_part1.txt");
+        let second = dir.join("merged_part2.txt");
+        fs::write(&first, "old").unwrap();
+        fs::write(dir.join("merged_part1.txt.partial"), "unrelated").unwrap();
+        let mut staged = vec![
+            stage_file(&first.to_string_lossy(), "new 1").unwrap(),
+            stage_file(&second.to_string_lossy(), "new 2").unwrap(),
+        ];
+        commit_staged(&mut staged).unwrap();
+        assert_eq!(fs::read_to_string(&first).unwrap(), "new 1");
+        rollback_staged(&mut staged);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "old");
+        assert!(!second.exists());
+        assert_eq!(fs::read_to_string(dir.join("merged_part1.txt.partial")).unwrap(), "unrelated");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn finish_removes_backups() {
+        let dir = scratch_dir("finish");
+        let target = dir.join("merged.txt");
+        fs::write(&target, "old").unwrap();
+        let mut staged = vec![stage_file(&target.to_string_lossy(), "new").unwrap()];
+        commit_staged(&mut staged).unwrap();
+        finish_staged(&staged);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
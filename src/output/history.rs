@@ -0,0 +1,43 @@
+use crate::ui::output::{OutputDestination, OutputFormat};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = "history.json";
+const MAX_HISTORY: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub source: String,
+    pub selected: Vec<String>,
+    pub output_file: String,
+    pub destination: OutputDestination,
+    pub format: OutputFormat,
+    pub timestamp: u64,
+}
+
+fn history_path() -> Option<PathBuf> {
+    crate::config::config_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+pub fn load_history() -> Vec<HistoryEntry> {
+    history_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn record_merge(history: &mut Vec<HistoryEntry>, entry: HistoryEntry) -> Result<(), String> {
+    history.retain(|e| {
+        !(e.source == entry.source
+            && e.selected == entry.selected
+            && e.output_file == entry.output_file
+            && e.format == entry.format)
+    });
+    history.insert(0, entry);
+    history.truncate(MAX_HISTORY);
+    let path = history_path().ok_or_else(|| "No config directory for the merge history".to_string())?;
+    let json = serde_json::to_string_pretty(history)
+        .map_err(|e| format!("Error serializing merge history: {}", e))?;
+    super::file::write_file(&path.to_string_lossy(), &json)
+}
@@ -1,39 +1,573 @@
 pub mod clipboard;
 pub mod file;
-use crate::input::SourceFile;
-use crate::ui::output::OutputDestination;
+pub mod history;
+pub mod pricing;
+pub mod redact;
+pub mod transform;
+pub mod tree;
+use crate::input::file_system::get_extension;
+use crate::input::{count_tokens_in_content, SourceFile, TextSourceError};
+use crate::ui::filters::extension_group;
+use crate::ui::output::{OutputDestination, OutputFormat};
 use crate::ui::App;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use thiserror::Error;
+
+#[derive(Serialize)]
+struct JsonFileEntry {
+    path: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    duplicate_paths: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    content: String,
+    tokens: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct ExtensionStats {
+    pub files: usize,
+    pub tokens: usize,
+}
+
+#[derive(Default)]
+pub struct MergeStats {
+    pub files: usize,
+    pub bytes: usize,
+    pub lines: usize,
+    pub words: usize,
+    pub by_extension: BTreeMap<String, ExtensionStats>,
+}
+
+impl MergeStats {
+    fn add(&mut self, entry: &MergedEntry) {
+        let content = &entry.content;
+        self.files += 1;
+        self.bytes += content.len();
+        self.lines += content.lines().count();
+        self.words += content.split_whitespace().count();
+        for (i, path) in entry.paths.iter().enumerate() {
+            let ext = extension_group(path);
+            let ext_stats = self.by_extension.entry(ext).or_default();
+            ext_stats.files += 1;
+            if i == 0 {
+                ext_stats.tokens += entry.tokens.unwrap_or(0);
+            }
+        }
+    }
+}
+
+pub struct MergeOutput {
+    pub content: String,
+    pub stats: MergeStats,
+    pub parts: usize,
+    pub omitted: usize,
+    pub redactions: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitPolicy {
+    Parts(usize),
+    MaxTokens(usize),
+    MaxBytes(usize),
+}
+
+pub struct MergeOptions {
+    pub format: OutputFormat,
+    pub deduplicate: bool,
+    pub max_output_bytes: Option<usize>,
+    pub strip_notebooks: bool,
+    pub preamble: Option<String>,
+    pub postamble: Option<String>,
+    pub file_summaries: bool,
+    pub skip_empty: bool,
+    pub tree_style: Option<tree::TreeStyle>,
+    pub split: Option<SplitPolicy>,
+    pub path_prefix: Option<String>,
+    pub max_file_tokens: Option<usize>,
+    pub order: Vec<String>,
+    pub table_of_contents: bool,
+    pub post_process: Option<String>,
+    pub redact_secrets: bool,
+    pub all_or_nothing: bool,
+    pub wrap_column: Option<usize>,
+}
+
+#[derive(Error, Debug)]
+pub enum MergeError {
+    #[error("Output exceeds the {limit} byte limit")]
+    TooLarge { limit: usize },
+    #[error("{0}")]
+    Failed(String),
+    #[error("Post-processor failed: {0}")]
+    PostProcess(String),
+    #[error("{0}")]
+    Source(#[from] TextSourceError),
+}
+
+impl From<String> for MergeError {
+    fn from(e: String) -> Self {
+        MergeError::Failed(e)
+    }
+}
+
+struct MergedEntry {
+    paths: Vec<String>,
+    url: Option<String>,
+    content: String,
+    tokens: Option<usize>,
+}
+
+fn deduplicate_entries(entries: Vec<MergedEntry>) -> Vec<MergedEntry> {
+    let mut unique: Vec<MergedEntry> = Vec::new();
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for entry in entries {
+        let mut hasher = DefaultHasher::new();
+        entry.content.hash(&mut hasher);
+        let candidates = by_hash.entry(hasher.finish()).or_default();
+        if let Some(&idx) = candidates
+            .iter()
+            .find(|&&idx| unique[idx].content == entry.content)
+        {
+            unique[idx].paths.extend(entry.paths);
+        } else {
+            candidates.push(unique.len());
+            unique.push(entry);
+        }
+    }
+    unique
+}
+
+pub(crate) const MERGE_BATCH_SIZE: usize = 64;
+
+fn render_toc(rows: &[(String, Option<usize>, usize)], shift: usize, format: &OutputFormat) -> String {
+    let tokens = |n: &Option<usize>| n.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string());
+    let mut toc = String::new();
+    match format {
+        OutputFormat::Text => {
+            toc.push_str("--- TABLE OF CONTENTS ---\n");
+            for (path, n, offset) in rows {
+                toc.push_str(&format!("{} | {} tokens | byte {}\n", path, tokens(n), offset + shift));
+            }
+            toc.push_str("--- END TABLE OF CONTENTS ---\n\n");
+        }
+        OutputFormat::Markdown => {
+            toc.push_str("### Contents\n\n| File | Tokens | Byte offset |\n| --- | ---: | ---: |\n");
+            for (path, n, offset) in rows {
+                toc.push_str(&format!("| `{}` | {} | {} |\n", path, tokens(n), offset + shift));
+            }
+            toc.push('\n');
+        }
+        OutputFormat::Json => {}
+    }
+    toc
+}
+
+fn markdown_fence(content: &str) -> String {
+    let longest_run = content
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
 
 pub async fn write_merged(
     destination: &OutputDestination,
+    options: &MergeOptions,
     output_file: &str,
     files_map: HashMap<String, SourceFile>,
     app: &mut App,
-) -> Result<String, String> {
-    let mut merged = String::new();
-    for (path, sf) in files_map {
-        if let Ok(content) = app.reload_file_content(&sf).await {
-            merged.push_str(&format!("--- START FILE: {} ---\n", path));
-            merged.push_str(&content);
-            merged.push_str(&format!("\n--- END FILE: {} ---\n\n", path));
+) -> Result<MergeOutput, MergeError> {
+    let mut entries = Vec::new();
+    let mut omitted = Vec::new();
+    let mut unfetched = Vec::new();
+    let mut redactions = 0;
+    let mut total_bytes = 0;
+    let mut files: Vec<(String, SourceFile)> = files_map.into_iter().collect();
+    let positions: HashMap<&str, usize> = options.order.iter().enumerate().map(|(i, p)| (p.as_str(), i)).collect();
+    files.sort_by(|(a, _), (b, _)| {
+        let rank = |path: &str| positions.get(path).copied().unwrap_or(usize::MAX);
+        rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+    });
+    for chunk in files.chunks(MERGE_BATCH_SIZE) {
+        let source_files: Vec<SourceFile> = chunk.iter().map(|(_, sf)| sf.clone()).collect();
+        let contents = app.reload_file_contents(&source_files).await;
+        for ((path, sf), result) in chunk.iter().zip(contents) {
+            let mut content = match result {
+                Ok(content) => content,
+                Err(
+                    e @ (TextSourceError::RateLimitExceeded(_)
+                    | TextSourceError::Timeout(_)
+                    | TextSourceError::NetworkError(_)),
+                ) => return Err(e.into()),
+                Err(
+                    TextSourceError::GitHubError(_)
+                    | TextSourceError::BitbucketError(_)
+                    | TextSourceError::PathNotFound(_),
+                ) => {
+                    unfetched.push(path.clone());
+                    continue;
+                }
+                Err(e) => {
+                    app.offer_text_override(&e, true);
+                    continue;
+                }
+            };
+            if options.skip_empty && content.is_empty() {
+                continue;
+            }
+            let is_notebook = get_extension(path).as_deref() == Some("ipynb");
+            if options.strip_notebooks && is_notebook {
+                if let Some(sources) = transform::notebook_sources(&content) {
+                    content = sources;
+                }
+            }
+            let mut redacted = 0;
+            if options.redact_secrets {
+                (content, redacted) = redact::redact_secrets(&content);
+                redactions += redacted;
+            }
+            let mut wrapped = false;
+            if let Some(column) = options.wrap_column {
+                if let Some(rewrapped) = transform::wrap_long_lines(&content, column) {
+                    content = rewrapped;
+                    wrapped = true;
+                }
+            }
+            let known_tokens = if (options.strip_notebooks && is_notebook) || redacted > 0 || wrapped {
+                None
+            } else {
+                app.source_files_panel.known_token_count(path)
+            };
+            let tokens = known_tokens.or_else(|| count_tokens_in_content(&content, app.tokenizer).ok());
+            let merged_path = match &options.path_prefix {
+                Some(prefix) => format!("{}/{}", prefix.trim_end_matches('/'), path),
+                None => path.clone(),
+            };
+            if let (Some(cap), Some(n)) = (options.max_file_tokens, tokens) {
+                if n > cap {
+                    omitted.push((merged_path, n));
+                    continue;
+                }
+            }
+            total_bytes += content.len();
+            if let Some(limit) = options.max_output_bytes {
+                if total_bytes > limit {
+                    return Err(MergeError::TooLarge { limit });
+                }
+            }
+            entries.push(MergedEntry {
+                paths: vec![merged_path],
+                url: app.web_url(sf),
+                content,
+                tokens,
+            });
+        }
+    }
+    let mut warnings = Vec::new();
+    if !unfetched.is_empty() {
+        let shown: Vec<&str> = unfetched.iter().take(3).map(|p| p.as_str()).collect();
+        let more = if unfetched.len() > shown.len() { ", …" } else { "" };
+        warnings.push(format!(
+            "{} files could not be fetched and were left out ({}{})",
+            unfetched.len(),
+            shown.join(", "),
+            more
+        ));
+    }
+    if *destination == OutputDestination::Directory {
+        let mut stats = MergeStats::default();
+        for entry in &entries {
+            file::write_file_in_dir(output_file, &entry.paths[0], &entry.content)?;
+            stats.add(entry);
         }
+        return Ok(MergeOutput {
+            content: String::new(),
+            stats,
+            parts: 1,
+            omitted: omitted.len(),
+            redactions,
+            warnings,
+        });
+    }
+    if options.deduplicate {
+        entries = deduplicate_entries(entries);
     }
-    match destination {
-        OutputDestination::FileAndClipboard | OutputDestination::File => {
-            if let Err(e) = file::write_file(output_file, &merged) {
-                return Err(e);
+    let tree = options.tree_style.map(|style| {
+        tree::render_tree(
+            entries.iter().flat_map(|e| e.paths.iter().map(|p| p.as_str())),
+            style,
+        )
+    });
+    let groups = match options.split {
+        Some(policy) => split_entries(entries, policy),
+        None => vec![entries],
+    };
+    let total_parts = groups.len();
+    let mut stats = MergeStats::default();
+    let mut parts = Vec::new();
+    for (index, group) in groups.into_iter().enumerate() {
+        let mut merged = String::new();
+        if total_parts > 1 {
+            match options.format {
+                OutputFormat::Text => {
+                    merged.push_str(&format!("--- PART {} OF {} ---\n\n", index + 1, total_parts));
+                }
+                OutputFormat::Markdown => {
+                    merged.push_str(&format!("## Part {} of {}\n\n", index + 1, total_parts));
+                }
+                OutputFormat::Json => {}
+            }
+        }
+        if options.format != OutputFormat::Json && index == 0 {
+            if let Some(preamble) = &options.preamble {
+                merged.push_str(preamble.trim_end());
+                merged.push_str("\n\n");
+            }
+        }
+        if let (Some(cap), false, 0) = (options.max_file_tokens, omitted.is_empty(), index) {
+            omitted.sort();
+            match options.format {
+                OutputFormat::Text => {
+                    merged.push_str(&format!("--- OMITTED FOR SIZE (over {} tokens) ---\n", cap));
+                    for (path, tokens) in &omitted {
+                        merged.push_str(&format!("{} ({} tokens)\n", path, tokens));
+                    }
+                    merged.push_str("--- END OMITTED ---\n\n");
+                }
+                OutputFormat::Markdown => {
+                    merged.push_str(&format!("### Omitted for size (over {} tokens)\n\n", cap));
+                    for (path, tokens) in &omitted {
+                        merged.push_str(&format!("- `{}` ({} tokens)\n", path, tokens));
+                    }
+                    merged.push('\n');
+                }
+                OutputFormat::Json => {}
+            }
+        }
+        if let (Some(tree), 0) = (&tree, index) {
+            match options.format {
+                OutputFormat::Text => {
+                    merged.push_str(&format!("--- FILE TREE ---\n{}--- END FILE TREE ---\n\n", tree));
+                }
+                OutputFormat::Markdown => {
+                    let fence = markdown_fence(tree);
+                    merged.push_str(&format!("### File tree\n\n{}\n{}{}\n\n", fence, tree, fence));
+                }
+                OutputFormat::Json => {}
+            }
+        }
+        let toc_at = merged.len();
+        let mut toc_rows = Vec::new();
+        let mut json_entries = Vec::new();
+        for entry in group {
+            stats.add(&entry);
+            toc_rows.push((entry.paths.join(", "), entry.tokens, merged.len()));
+            match options.format {
+                OutputFormat::Text => {
+                    let header = entry.paths.join(", ");
+                    if options.file_summaries {
+                        let tokens = entry
+                            .tokens
+                            .map(|n| n.to_string())
+                            .unwrap_or_else(|| "?".to_string());
+                        merged.push_str(&format!(
+                            "--- START FILE: {} | {} tokens | {} lines ---\n",
+                            header,
+                            tokens,
+                            entry.content.lines().count()
+                        ));
+                    } else {
+                        merged.push_str(&format!("--- START FILE: {} ---\n", header));
+                    }
+                    merged.push_str(entry.content.trim_end_matches(['\r', '\n']));
+                    merged.push_str(&format!("\n--- END FILE: {} ---\n\n", header));
+                }
+                OutputFormat::Markdown => {
+                    let header = entry.paths.join(", ");
+                    match &entry.url {
+                        Some(url) => merged.push_str(&format!("### [{}]({})\n\n", header, url)),
+                        None => merged.push_str(&format!("### {}\n\n", header)),
+                    }
+                    if options.file_summaries {
+                        let tokens = entry
+                            .tokens
+                            .map(|n| n.to_string())
+                            .unwrap_or_else(|| "?".to_string());
+                        merged.push_str(&format!(
+                            "{} tokens | {} lines\n\n",
+                            tokens,
+                            entry.content.lines().count()
+                        ));
+                    }
+                    let fence = markdown_fence(&entry.content);
+                    let language = get_extension(&entry.paths[0]).unwrap_or_default();
+                    merged.push_str(&format!("{}{}\n", fence, language));
+                    merged.push_str(entry.content.trim_end_matches(['\r', '\n']));
+                    merged.push_str(&format!("\n{}\n\n", fence));
+                }
+                OutputFormat::Json => {
+                    let tokens = entry.tokens;
+                    let mut paths = entry.paths.into_iter();
+                    json_entries.push(JsonFileEntry {
+                        path: paths.next().unwrap_or_default(),
+                        duplicate_paths: paths.collect(),
+                        url: entry.url,
+                        content: entry.content,
+                        tokens,
+                    });
+                }
+            }
+        }
+        if options.table_of_contents && options.format != OutputFormat::Json {
+            let mut toc = String::new();
+            loop {
+                let next = render_toc(&toc_rows, toc.len(), &options.format);
+                let settled = next.len() == toc.len();
+                toc = next;
+                if settled {
+                    break;
+                }
+            }
+            merged.insert_str(toc_at, &toc);
+        }
+        if options.format != OutputFormat::Json && index + 1 == total_parts {
+            if let Some(postamble) = &options.postamble {
+                merged.push_str(postamble.trim_end());
+                merged.push('\n');
             }
         }
-        _ => {}
+        if options.format == OutputFormat::Json {
+            merged = serde_json::to_string_pretty(&json_entries)
+                .map_err(|e| format!("Error serializing JSON: {}", e))?;
+        }
+        if let Some(command) = &options.post_process {
+            merged = transform::post_process(command, &merged).map_err(MergeError::PostProcess)?;
+        }
+        parts.push(merged);
     }
-    if matches!(
+    let targets: Vec<(String, &String)> = match destination {
+        OutputDestination::FileAndClipboard | OutputDestination::File if total_parts == 1 => {
+            vec![(output_file.to_string(), &parts[0])]
+        }
+        OutputDestination::FileAndClipboard | OutputDestination::File => parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| (file::part_path(output_file, index + 1), part))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let copy = matches!(
         destination,
         OutputDestination::FileAndClipboard | OutputDestination::Clipboard
-    ) {
-        if let Err(e) = clipboard::copy_clipboard(merged.clone()) {
-            return Err(e);
+    );
+    let content = parts.first().cloned().unwrap_or_default();
+    if options.all_or_nothing {
+        let mut staged = Vec::new();
+        for (path, part) in &targets {
+            match file::stage_file(path, part) {
+                Ok(file) => staged.push(file),
+                Err(e) => {
+                    file::rollback_staged(&mut staged);
+                    return Err(format!("{}  •  nothing was written", e).into());
+                }
+            }
+        }
+        if let Err(e) = file::commit_staged(&mut staged) {
+            return Err(format!("{}  •  nothing was written or copied", e).into());
+        }
+        if copy {
+            if let Err(e) = clipboard::copy_clipboard(content.clone()) {
+                file::rollback_staged(&mut staged);
+                return Err(format!("{}  •  nothing was written, earlier files were restored", e).into());
+            }
+        }
+        file::finish_staged(&staged);
+    } else {
+        let mut file_error = None;
+        for (path, part) in &targets {
+            if let Err(e) = file::write_file(path, part) {
+                file_error = Some(e);
+                break;
+            }
+        }
+        let clipboard_error = if copy {
+            clipboard::copy_clipboard(content.clone()).err()
+        } else {
+            None
+        };
+        match (file_error, clipboard_error) {
+            (Some(e), None) if copy => warnings.push(format!("File not written: {}", e)),
+            (None, Some(e)) if !targets.is_empty() => warnings.push(format!("Not copied: {}", e)),
+            (Some(e), _) | (None, Some(e)) => return Err(e.into()),
+            (None, None) => {}
         }
     }
-    Ok(merged)
+    if copy && total_parts > 1 {
+        warnings.push(if targets.is_empty() {
+            format!(
+                "Only part 1 of {} was copied to the clipboard, the rest was discarded; pick a file destination to keep every part",
+                total_parts
+            )
+        } else {
+            format!("Only part 1 of {} was copied to the clipboard; every part was written", total_parts)
+        });
+    }
+    Ok(MergeOutput {
+        content,
+        stats,
+        parts: total_parts,
+        omitted: omitted.len(),
+        redactions,
+        warnings,
+    })
+}
+
+fn split_entries(entries: Vec<MergedEntry>, policy: SplitPolicy) -> Vec<Vec<MergedEntry>> {
+    let weight = |entry: &MergedEntry| match policy {
+        SplitPolicy::MaxBytes(_) => entry.content.len(),
+        _ => entry.tokens.unwrap_or(entry.content.len() / 4),
+    };
+    let mut groups: Vec<Vec<MergedEntry>> = Vec::new();
+    match policy {
+        SplitPolicy::Parts(n) => {
+            let n = n.max(1);
+            let total: usize = entries.iter().map(weight).sum::<usize>().max(1);
+            let mut before = 0;
+            for entry in entries {
+                let index = (before * n / total).min(n - 1);
+                before += weight(&entry);
+                while groups.len() <= index {
+                    groups.push(Vec::new());
+                }
+                groups[index].push(entry);
+            }
+            groups.retain(|g| !g.is_empty());
+        }
+        SplitPolicy::MaxTokens(limit) | SplitPolicy::MaxBytes(limit) => {
+            let mut current = 0;
+            for entry in entries {
+                let w = weight(&entry);
+                match groups.last_mut() {
+                    Some(group) if current + w <= limit => {
+                        group.push(entry);
+                        current += w;
+                    }
+                    _ => {
+                        groups.push(vec![entry]);
+                        current = w;
+                    }
+                }
+            }
+        }
+    }
+    if groups.is_empty() {
+        groups.push(Vec::new());
+    }
+    groups
 }
@@ -0,0 +1,59 @@
+use std::fs;
+
+const PRICING_FILE: &str = "pricing.txt";
+
+const DEFAULT_PRICES: &[(&str, f64)] = &[
+    ("gpt-4.1", 2.00),
+    ("gpt-4o", 2.50),
+    ("o3", 2.00),
+    ("claude-sonnet", 3.00),
+    ("claude-opus", 15.00),
+    ("gemini-2.5-pro", 1.25),
+];
+
+#[derive(Clone, Debug)]
+pub struct ModelPrice {
+    pub name: String,
+    pub usd_per_mtok: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, tokens: usize) -> f64 {
+        tokens as f64 * self.usd_per_mtok / 1_000_000.0
+    }
+}
+
+pub fn load_pricing() -> Vec<ModelPrice> {
+    let custom = crate::config::config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(PRICING_FILE)).ok());
+    match custom {
+        Some(content) => content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (name, price) = line.rsplit_once(char::is_whitespace)?;
+                let usd_per_mtok = price.trim_start_matches('$').parse().ok()?;
+                Some(ModelPrice {
+                    name: name.trim().to_string(),
+                    usd_per_mtok,
+                })
+            })
+            .collect(),
+        None => DEFAULT_PRICES
+            .iter()
+            .map(|(name, price)| ModelPrice {
+                name: name.to_string(),
+                usd_per_mtok: *price,
+            })
+            .collect(),
+    }
+}
+
+pub fn format_cost(usd: f64) -> String {
+    if usd > 0.0 && usd < 0.01 {
+        "<$0.01".to_string()
+    } else {
+        format!("${:.2}", usd)
+    }
+}
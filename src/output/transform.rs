@@ -0,0 +1,165 @@
+use serde_json::Value;
+use crate::config::env_value;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+const DEFAULT_POST_PROCESS_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn notebook_sources(content: &str) -> Option<String> {
+    let notebook: Value = serde_json::from_str(content).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+    let mut out = String::new();
+    for cell in cells {
+        let marker = match cell.get("cell_type").and_then(Value::as_str) {
+            Some("code") => "# %%",
+            Some("markdown") => "# %% [markdown]",
+            _ => continue,
+        };
+        let source = match cell.get("source") {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+            _ => continue,
+        };
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(marker);
+        out.push('\n');
+        out.push_str(source.trim_end());
+        out.push('\n');
+    }
+    Some(out)
+}
+
+pub const CONTINUATION_MARKER: &str = "↪ ";
+
+pub fn wrap_long_lines(content: &str, column: usize) -> Option<String> {
+    let column = column.max(CONTINUATION_MARKER.chars().count() + 1);
+    if !content.lines().any(|l| l.chars().count() > column) {
+        return None;
+    }
+    let mut out = String::with_capacity(content.len() + content.len() / column * 4);
+    for (i, line) in content.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut chars = line.chars().peekable();
+        let mut width = column;
+        let mut first = true;
+        while chars.peek().is_some() {
+            if !first {
+                out.push('\n');
+                out.push_str(CONTINUATION_MARKER);
+            }
+            out.extend(chars.by_ref().take(width));
+            width = column - CONTINUATION_MARKER.chars().count();
+            first = false;
+        }
+    }
+    Some(out)
+}
+
+pub fn post_process(command: &str, input: &str) -> Result<String, String> {
+    let timeout = env_value("ANVIL_POST_PROCESS_TIMEOUT")
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_POST_PROCESS_TIMEOUT);
+    run_filter(command, input, timeout)
+}
+
+fn run_filter(command: &str, input: &str, timeout: Duration) -> Result<String, String> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let child = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not start `{}`: {}", command, e))?;
+    let stdout = wait_for_output(child, Some(input.to_string()), command, timeout)?;
+    String::from_utf8(stdout).map_err(|_| format!("`{}` produced output that is not UTF-8", command))
+}
+
+/// Feeds `input` to a child spawned with piped stdout and stderr (and stdin
+/// when `input` is given), killing it once `timeout` passes. Returns its
+/// stdout, or the first line of stderr if it exits unsuccessfully.
+pub(crate) fn wait_for_output(
+    mut child: Child,
+    input: Option<String>,
+    command: &str,
+    timeout: Duration,
+) -> Result<Vec<u8>, String> {
+    let writer = match input {
+        Some(input) => {
+            let mut stdin = child.stdin.take().ok_or("could not open stdin")?;
+            Some(std::thread::spawn(move || stdin.write_all(input.as_bytes())))
+        }
+        None => None,
+    };
+    let mut stdout = child.stdout.take().ok_or("could not open stdout")?;
+    let mut stderr = child.stderr.take().ok_or("could not open stderr")?;
+    let out_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        stdout.read_to_end(&mut buf).map(|_| buf)
+    });
+    let err_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        stderr.read_to_end(&mut buf).map(|_| buf)
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("`{}` did not finish within {}s and was stopped", command, timeout.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(format!("`{}` did not finish: {}", command, e)),
+        }
+    };
+    if let Some(Ok(Err(e))) = writer.map(|w| w.join()) {
+        if e.kind() != std::io::ErrorKind::BrokenPipe {
+            return Err(format!("could not write to `{}`: {}", command, e));
+        }
+    }
+    let stdout = out_reader
+        .join()
+        .map_err(|_| format!("could not read from `{}`", command))?
+        .map_err(|e| format!("could not read from `{}`: {}", command, e))?;
+    if !status.success() {
+        let stderr = err_reader.join().ok().and_then(|r| r.ok()).unwrap_or_default();
+        let stderr = String::from_utf8_lossy(&stderr);
+        let detail = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("no error output");
+        return Err(format!("`{}` exited with {}: {}", command, status, detail.trim()));
+    }
+    Ok(stdout)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::run_filter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn pipes_input_through_the_command() {
+        assert_eq!(run_filter("tr a-z A-Z", "merged", Duration::from_secs(5)).unwrap(), "MERGED");
+    }
+
+    #[test]
+    fn stops_a_hanging_command() {
+        let started = Instant::now();
+        let err = run_filter("sleep 30", "", Duration::from_millis(300)).unwrap_err();
+        assert!(err.contains("did not finish"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn reports_the_first_error_line() {
+        let err = run_filter("echo broken >&2; exit 3", "", Duration::from_secs(5)).unwrap_err();
+        assert!(err.contains("broken"), "{}", err);
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TreeStyle {
+    BoxDrawing,
+    Ascii,
+    Indent,
+}
+
+impl TreeStyle {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TreeStyle::BoxDrawing => "box",
+            TreeStyle::Ascii => "ascii",
+            TreeStyle::Indent => "indent",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "box" => Some(TreeStyle::BoxDrawing),
+            "ascii" => Some(TreeStyle::Ascii),
+            "indent" => Some(TreeStyle::Indent),
+            _ => None,
+        }
+    }
+    fn connectors(&self, last: bool) -> (&'static str, &'static str) {
+        match (self, last) {
+            (TreeStyle::BoxDrawing, false) => ("├── ", "│   "),
+            (TreeStyle::BoxDrawing, true) => ("└── ", "    "),
+            (TreeStyle::Ascii, false) => ("|-- ", "|   "),
+            (TreeStyle::Ascii, true) => ("`-- ", "    "),
+            (TreeStyle::Indent, _) => ("", "  "),
+        }
+    }
+}
+
+#[derive(Default)]
+struct TreeNode {
+    children: BTreeMap<String, TreeNode>,
+}
+
+pub fn render_tree<'a>(paths: impl IntoIterator<Item = &'a str>, style: TreeStyle) -> String {
+    let mut root = TreeNode::default();
+    for path in paths {
+        let mut node = &mut root;
+        for part in path.split('/').filter(|p| !p.is_empty()) {
+            node = node.children.entry(part.to_string()).or_default();
+        }
+    }
+    let mut out = String::new();
+    render_children(&root, "", style, &mut out);
+    out
+}
+
+fn render_children(node: &TreeNode, prefix: &str, style: TreeStyle, out: &mut String) {
+    let count = node.children.len();
+    for (i, (name, child)) in node.children.iter().enumerate() {
+        let (branch, continuation) = style.connectors(i + 1 == count);
+        out.push_str(prefix);
+        out.push_str(branch);
+        out.push_str(name);
+        if !child.children.is_empty() {
+            out.push('/');
+        }
+        out.push('\n');
+        render_children(child, &format!("{}{}", prefix, continuation), style, out);
+    }
+}
@@ -0,0 +1,73 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use crate::config::config_dir;
+use crate::ui::source_files::format_number;
+
+const SKIP_CONFIRM_FILE: &str = "skip-merge-confirm";
+
+pub fn confirm_skipped() -> bool {
+    config_dir().is_some_and(|dir| dir.join(SKIP_CONFIRM_FILE).exists())
+}
+
+pub fn set_confirm_skipped(skipped: bool) -> Result<(), String> {
+    let dir = config_dir().ok_or("No config directory to remember the choice in")?;
+    let marker = dir.join(SKIP_CONFIRM_FILE);
+    if skipped {
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+        std::fs::write(&marker, "").map_err(|e| format!("Could not write {}: {}", marker.display(), e))
+    } else if marker.exists() {
+        std::fs::remove_file(&marker).map_err(|e| format!("Could not remove {}: {}", marker.display(), e))
+    } else {
+        Ok(())
+    }
+}
+
+pub struct MergeConfirm {
+    pub files: usize,
+    pub tokens: usize,
+    pub uncounted: usize,
+    pub destination: String,
+    pub output_path: Option<String>,
+    pub format: String,
+}
+
+impl MergeConfirm {
+    pub fn draw(&self, f: &mut ratatui::Frame, area: Rect) {
+        let mut tokens = format_number(self.tokens);
+        if self.uncounted > 0 {
+            tokens = format!("{} (+{} files not counted yet)", tokens, format_number(self.uncounted));
+        }
+        let mut lines = vec![
+            Line::from(format!("Files:        {}", format_number(self.files))),
+            Line::from(format!("Tokens:       {}", tokens)),
+            Line::from(format!("Destination:  {}", self.destination)),
+        ];
+        if let Some(path) = &self.output_path {
+            lines.push(Line::from(format!("Output path:  {}", path)));
+        }
+        lines.push(Line::from(format!("Format:       {}", self.format)));
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "enter - merge  •  esc - cancel  •  a - don't ask again",
+            Style::default().fg(Color::DarkGray),
+        ));
+        let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4;
+        let height = lines.len() as u16 + 2;
+        let rect = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width: width.min(area.width),
+            height: height.min(area.height),
+        };
+        let block = Block::default()
+            .title("Merge?")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White).bg(Color::Black));
+        f.render_widget(Clear, rect);
+        f.render_widget(Paragraph::new(lines).block(block), rect);
+    }
+}
@@ -0,0 +1,84 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use std::time::{Duration, Instant};
+use crate::input::github::api_request_count;
+use crate::ui::source_files::format_number;
+
+pub struct OperationTiming {
+    pub duration: Duration,
+    pub files: usize,
+    pub requests: usize,
+}
+
+pub struct OperationTimer {
+    started: Instant,
+    requests: usize,
+}
+
+impl OperationTimer {
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            requests: api_request_count(),
+        }
+    }
+    pub fn finish(&self, files: usize) -> OperationTiming {
+        OperationTiming {
+            duration: self.started.elapsed(),
+            files,
+            requests: api_request_count().saturating_sub(self.requests),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DebugPanel {
+    pub visible: bool,
+    pub index: Option<OperationTiming>,
+    pub count: Option<OperationTiming>,
+    pub merge: Option<OperationTiming>,
+}
+
+impl DebugPanel {
+    pub fn draw(&self, f: &mut ratatui::Frame, area: Rect, concurrency: usize) {
+        let width = 46.min(area.width);
+        let height = 7.min(area.height);
+        let rect = Rect {
+            x: area.x + area.width - width,
+            y: area.y,
+            width,
+            height,
+        };
+        let row = |label: &str, timing: &Option<OperationTiming>| match timing {
+            Some(t) => Line::from(format!(
+                "{:<7}{:>8.2}s  {:>7} files  {:>5} req",
+                label,
+                t.duration.as_secs_f64(),
+                format_number(t.files),
+                format_number(t.requests)
+            )),
+            None => Line::from(format!("{:<7}       -", label)),
+        };
+        let lines = vec![
+            row("index", &self.index),
+            row("tokens", &self.count),
+            row("merge", &self.merge),
+            Line::from(""),
+            Line::from(format!(
+                "API requests total: {}  concurrency: {}",
+                format_number(api_request_count()),
+                concurrency
+            )),
+        ];
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Debug (F12)")
+            .style(Style::default().fg(Color::White).bg(Color::Black));
+        f.render_widget(Clear, rect);
+        f.render_widget(Paragraph::new(lines).block(block), rect);
+    }
+}
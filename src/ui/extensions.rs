@@ -0,0 +1,105 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
+use std::collections::BTreeSet;
+use crate::input::{FilterConfig, DEFAULT_BINARY_EXTENSIONS};
+
+pub struct ExtensionsPanel {
+    pub items: Vec<String>,
+    pub cursor: usize,
+    pub offset: usize,
+    pub changed: bool,
+}
+
+impl ExtensionsPanel {
+    pub fn new(filter_config: &FilterConfig, file_extensions: impl Iterator<Item = String>) -> Self {
+        let mut items: BTreeSet<String> = DEFAULT_BINARY_EXTENSIONS.iter().map(|e| e.to_string()).collect();
+        items.extend(filter_config.binary_extensions.iter().cloned());
+        items.extend(filter_config.additional_text_extensions.iter().cloned());
+        items.extend(filter_config.additional_binary_extensions.iter().cloned());
+        items.extend(filter_config.converters.keys().cloned());
+        items.extend(file_extensions);
+        Self {
+            items: items.into_iter().collect(),
+            cursor: 0,
+            offset: 0,
+            changed: false,
+        }
+    }
+
+    pub fn draw(&mut self, f: &mut ratatui::Frame, area: Rect, filter_config: &FilterConfig) {
+        let visible_count = area.height.saturating_sub(2) as usize;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if visible_count > 0 && self.cursor >= self.offset + visible_count {
+            self.offset = self.cursor + 1 - visible_count;
+        }
+        let name_width = self.items.iter().map(|it| it.len()).max().unwrap_or(0);
+        let items: Vec<ListItem> = self
+            .items
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(visible_count)
+            .map(|(i, ext)| {
+                let binary = filter_config.is_binary_extension(ext);
+                let built_in = DEFAULT_BINARY_EXTENSIONS.contains(&ext.as_str());
+                let note = match (binary, built_in) {
+                    (true, true) | (false, false) => "",
+                    _ => "  (changed)",
+                };
+                let prefix = if i == self.cursor { "> " } else { "  " };
+                let style = if i == self.cursor {
+                    Style::default().fg(Color::LightBlue)
+                } else if binary {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                let class = if filter_config.converters.contains_key(ext) {
+                    "convert"
+                } else if binary {
+                    "binary"
+                } else {
+                    "text"
+                };
+                ListItem::new(format!("{}.{:width$}  {}{}", prefix, ext, class, note, width = name_width))
+                    .style(style)
+            })
+            .collect();
+        let block = Block::default()
+            .title("Extension classes (space - text/binary, esc - close & reload)")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::LightBlue));
+        f.render_widget(Clear, area);
+        f.render_widget(List::new(items).block(block), area);
+    }
+
+    pub fn handle_input(&mut self, key: KeyEvent, filter_config: &mut FilterConfig) {
+        match key.code {
+            KeyCode::Up => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.cursor = (self.cursor + 1).min(self.items.len().saturating_sub(1));
+            }
+            KeyCode::PageUp => {
+                self.cursor = self.cursor.saturating_sub(10);
+            }
+            KeyCode::PageDown => {
+                self.cursor = (self.cursor + 10).min(self.items.len().saturating_sub(1));
+            }
+            KeyCode::Char(' ') => {
+                if let Some(ext) = self.items.get(self.cursor) {
+                    let binary = filter_config.is_binary_extension(ext);
+                    filter_config.set_binary_extension(ext, !binary);
+                    self.changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -2,15 +2,33 @@ use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     layout::Rect,
     style::{Color, Style},
-    widgets::{Block, Borders, ListItem},
+    text::Line,
+    widgets::{Block, Borders, ListItem, Paragraph},
 };
-use std::collections::{BTreeSet, HashSet};
-use crate::input::SourceFile;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::input::{file_system::get_extension, format_window, FilterConfig, SourceFile};
+use crate::ui::source_files::TokenStatus;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExtensionKind {
+    Text,
+    Binary,
+}
 
 pub struct FiltersPanel {
     pub items: Vec<String>,
     pub cursor: usize,
     pub offset: usize,
+    pub visible_height: usize,
+    pub extension_input: Option<(ExtensionKind, String)>,
+    pub sort_by_tokens: bool,
+    pub weights: HashMap<String, ExtensionWeight>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct ExtensionWeight {
+    pub files: usize,
+    pub tokens: usize,
 }
 
 impl FiltersPanel {
@@ -19,6 +37,10 @@ impl FiltersPanel {
             items: vec![],
             cursor: 0,
             offset: 0,
+            visible_height: 10,
+            extension_input: None,
+            sort_by_tokens: false,
+            weights: HashMap::new(),
         }
     }
     pub fn init_values(
@@ -27,17 +49,7 @@ impl FiltersPanel {
         selected_exts: &mut HashSet<String>,
         selected_files: &mut HashSet<String>,
     ) {
-        let mut exts = BTreeSet::new();
-        for f in files {
-            if let Some(ext) = f.path.split('.').last() {
-                exts.insert(ext.to_string());
-            }
-        }
-        let mut new_items = vec!["*".to_string()];
-        for e in exts {
-            new_items.push(e);
-        }
-        self.items = new_items;
+        self.items = collect_items(files);
         for it in &self.items {
             selected_exts.insert(it.clone());
         }
@@ -47,25 +59,89 @@ impl FiltersPanel {
         self.cursor = 0;
         self.offset = 0;
     }
+    pub fn refresh_items(&mut self, files: &[SourceFile]) {
+        self.items = collect_items(files);
+        if self.cursor >= self.items.len() {
+            self.cursor = self.items.len().saturating_sub(1);
+        }
+        self.offset = self.offset.min(self.cursor);
+    }
+    pub fn ensure_cursor_visible(&mut self, visible_height: usize) {
+        if self.cursor >= self.items.len() {
+            self.cursor = self.items.len().saturating_sub(1);
+        }
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if visible_height > 0 && self.cursor >= self.offset + visible_height {
+            self.offset = self.cursor + 1 - visible_height;
+        }
+    }
     pub fn draw(
-        &self,
+        &mut self,
         f: &mut ratatui::Frame,
         area: Rect,
         focused: bool,
         selected_exts: &HashSet<String>,
+        filter_config: &FilterConfig,
     ) {
+        let custom_lines: Vec<Line> = [
+            ("text", &filter_config.additional_text_extensions),
+            ("binary", &filter_config.additional_binary_extensions),
+        ]
+        .into_iter()
+        .filter(|(_, exts)| !exts.is_empty())
+        .map(|(label, exts)| {
+            let mut sorted: Vec<&String> = exts.iter().collect();
+            sorted.sort();
+            let list: Vec<String> = sorted.iter().map(|e| format!(".{}", e)).collect();
+            Line::styled(format!("{}: {}", label, list.join(" ")), Style::default().fg(Color::DarkGray))
+        })
+        .collect();
         let block_style = if focused {
             Style::default().fg(Color::LightBlue)
         } else {
             Style::default()
         };
+        let visible_count = (area.height.saturating_sub(2) as usize).saturating_sub(custom_lines.len());
+        self.visible_height = visible_count;
+        self.ensure_cursor_visible(self.visible_height);
+        let mut flags = Vec::new();
+        if filter_config.is_allowlist_mode() {
+            flags.push("allowlist");
+        }
+        if filter_config.no_ext_filter {
+            flags.push("no extension filter");
+        }
+        if !filter_config.respect_gitignore {
+            flags.push("gitignore off");
+        }
+        let window = filter_config.modified_within.map(|w| format!("modified within {}", format_window(w)));
+        if let Some(window) = &window {
+            flags.push(window);
+        }
+        if self.sort_by_tokens {
+            flags.push("by tokens");
+        }
+        let mut title = if flags.is_empty() {
+            "Filters".to_string()
+        } else {
+            format!("Filters ({})", flags.join(", "))
+        };
+        if let Some((kind, value)) = &self.extension_input {
+            let label = match kind {
+                ExtensionKind::Text => "text",
+                ExtensionKind::Binary => "binary",
+            };
+            title = format!("{} - {}: {}█", title, label, value);
+        }
         let block = Block::default()
-            .title("Filters")
+            .title(title)
             .borders(Borders::ALL)
             .style(block_style);
-        let visible_count = area.height.saturating_sub(2) as usize;
+        let inner = block.inner(area);
         let end = (self.offset + visible_count).min(self.items.len());
         let slice = &self.items[self.offset..end];
+        let name_width = self.items.iter().map(|it| it.len()).max().unwrap_or(0);
         let list_items: Vec<ListItem> = slice
             .iter()
             .enumerate()
@@ -83,34 +159,61 @@ impl FiltersPanel {
                         Style::default().fg(Color::DarkGray)
                     }
                 };
-                let line = format!("{}{} {}", prefix, icon, it);
+                let weight = self.weights.get(it).copied().unwrap_or_default();
+                let mut line = format!("{}{} {:width$}  ({} files", prefix, icon, it, weight.files, width = name_width);
+                if weight.tokens > 0 {
+                    line.push_str(&format!(", {}", format_compact(weight.tokens)));
+                }
+                line.push(')');
                 ListItem::new(line).style(item_style)
             })
             .collect();
         let list = ratatui::widgets::List::new(list_items).block(block);
         f.render_widget(list, area);
+        if !custom_lines.is_empty() {
+            let height = (custom_lines.len() as u16).min(inner.height);
+            let custom_area = Rect {
+                y: inner.y + inner.height - height,
+                height,
+                ..inner
+            };
+            f.render_widget(Paragraph::new(custom_lines), custom_area);
+        }
+    }
+    pub fn update_weights(&mut self, files: &[SourceFile], statuses: &HashMap<String, TokenStatus>) {
+        self.weights = extension_weights(files, statuses);
+        self.sort_items();
+    }
+    pub fn sort_items(&mut self) {
+        let current = self.items.get(self.cursor).cloned();
+        let weights = &self.weights;
+        let tokens = |it: &String| weights.get(it).map(|w| w.tokens).unwrap_or(0);
+        if let Some(rest) = self.items.get_mut(1..) {
+            if self.sort_by_tokens {
+                rest.sort_by(|a, b| tokens(b).cmp(&tokens(a)).then(a.cmp(b)));
+            } else {
+                rest.sort();
+            }
+        }
+        if let Some(current) = current {
+            self.cursor = self.items.iter().position(|it| *it == current).unwrap_or(0);
+        }
     }
     pub fn handle_input(&mut self, key_event: KeyEvent) {
         match key_event.code {
             KeyCode::Up => {
                 if self.cursor > 0 {
                     self.cursor -= 1;
-                    if self.cursor < self.offset {
-                        self.offset = self.cursor;
-                    }
                 }
             }
             KeyCode::Down => {
                 if self.cursor + 1 < self.items.len() {
                     self.cursor += 1;
-                    let visible_count = 10;
-                    if self.cursor >= self.offset + visible_count {
-                        self.offset = self.cursor + 1 - visible_count;
-                    }
                 }
             }
             _ => {}
         }
+        self.ensure_cursor_visible(self.visible_height);
     }
     pub fn toggle_selected(
         &mut self,
@@ -121,7 +224,16 @@ impl FiltersPanel {
         if self.items.is_empty() {
             return;
         }
-        let selected_item = &self.items[self.cursor];
+        let selected_item = self.items[self.cursor].clone();
+        self.toggle_extension(&selected_item, selected_exts, selected_files, all_files);
+    }
+    pub fn toggle_extension(
+        &self,
+        selected_item: &str,
+        selected_exts: &mut HashSet<String>,
+        selected_files: &mut HashSet<String>,
+        all_files: &Vec<SourceFile>,
+    ) {
         let is_already_selected = selected_exts.contains(selected_item);
         if selected_item == "*" {
             if is_already_selected {
@@ -144,20 +256,16 @@ impl FiltersPanel {
             if is_already_selected {
                 selected_exts.remove(selected_item);
                 for f in all_files {
-                    if let Some(ext) = f.path.split('.').last() {
-                        if ext == selected_item {
-                            selected_files.remove(&f.path);
-                        }
+                    if extension_group(&f.path) == selected_item {
+                        selected_files.remove(&f.path);
                     }
                 }
                 selected_exts.remove("*");
             } else {
-                selected_exts.insert(selected_item.clone());
+                selected_exts.insert(selected_item.to_string());
                 for f in all_files {
-                    if let Some(ext) = f.path.split('.').last() {
-                        if ext == selected_item {
-                            selected_files.insert(f.path.clone());
-                        }
+                    if extension_group(&f.path) == selected_item {
+                        selected_files.insert(f.path.clone());
                     }
                 }
                 let all_included = self.items.iter().all(|it| selected_exts.contains(it));
@@ -167,4 +275,50 @@ impl FiltersPanel {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+pub const NO_EXTENSION: &str = "(no ext)";
+
+pub fn extension_group(path: &str) -> String {
+    get_extension(path).unwrap_or_else(|| NO_EXTENSION.to_string())
+}
+
+fn collect_items(files: &[SourceFile]) -> Vec<String> {
+    let mut exts = BTreeSet::new();
+    for f in files {
+        exts.insert(extension_group(&f.path));
+    }
+    let mut items = vec!["*".to_string()];
+    for e in exts {
+        items.push(e);
+    }
+    items
+}
+
+pub fn extension_weights(
+    files: &[SourceFile],
+    statuses: &HashMap<String, TokenStatus>,
+) -> HashMap<String, ExtensionWeight> {
+    let mut weights: HashMap<String, ExtensionWeight> = HashMap::new();
+    for f in files {
+        let tokens = match statuses.get(&f.path) {
+            Some(TokenStatus::Done(n)) => *n,
+            _ => 0,
+        };
+        for key in ["*".to_string(), extension_group(&f.path)] {
+            let weight = weights.entry(key).or_default();
+            weight.files += 1;
+            weight.tokens += tokens;
+        }
+    }
+    weights
+}
+
+fn format_compact(n: usize) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=9_999 => format!("{:.1}k", n as f64 / 1_000.0),
+        10_000..=999_999 => format!("{}k", n / 1_000),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
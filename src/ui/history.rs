@@ -0,0 +1,75 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
+use crate::output::file::format_date;
+use crate::output::history::HistoryEntry;
+use crate::ui::output::OutputDestination;
+use crate::ui::source_files::format_number;
+
+pub struct HistoryPanel {
+    pub cursor: usize,
+    pub offset: usize,
+}
+
+impl HistoryPanel {
+    pub fn new() -> Self {
+        Self { cursor: 0, offset: 0 }
+    }
+
+    pub fn draw(&mut self, f: &mut ratatui::Frame, area: Rect, entries: &[HistoryEntry]) {
+        let visible_count = area.height.saturating_sub(2) as usize;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if visible_count > 0 && self.cursor >= self.offset + visible_count {
+            self.offset = self.cursor + 1 - visible_count;
+        }
+        let items: Vec<ListItem> = entries
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(visible_count)
+            .map(|(i, entry)| {
+                let target = match entry.destination {
+                    OutputDestination::Clipboard => "clipboard".to_string(),
+                    _ => entry.output_file.clone(),
+                };
+                let prefix = if i == self.cursor { "> " } else { "  " };
+                let style = if i == self.cursor {
+                    Style::default().fg(Color::LightBlue)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                ListItem::new(format!(
+                    "{}{}  {}  ({} files -> {})",
+                    prefix,
+                    format_date(entry.timestamp),
+                    entry.source,
+                    format_number(entry.selected.len()),
+                    target
+                ))
+                .style(style)
+            })
+            .collect();
+        let block = Block::default()
+            .title("Merge history (enter - re-run, esc - close)")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::LightBlue));
+        f.render_widget(Clear, area);
+        f.render_widget(List::new(items).block(block), area);
+    }
+
+    pub fn handle_input(&mut self, key: KeyEvent, len: usize) {
+        match key.code {
+            KeyCode::Up => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.cursor = (self.cursor + 1).min(len.saturating_sub(1));
+            }
+            _ => {}
+        }
+    }
+}
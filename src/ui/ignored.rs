@@ -0,0 +1,88 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
+
+pub struct IgnoredPanel {
+    pub items: Vec<(String, String)>,
+    pub cursor: usize,
+    pub offset: usize,
+}
+
+impl IgnoredPanel {
+    pub fn new(items: Vec<(String, String)>) -> Self {
+        Self {
+            items,
+            cursor: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn draw(&mut self, f: &mut ratatui::Frame, area: Rect, respect_gitignore: bool) {
+        let visible_count = area.height.saturating_sub(2) as usize;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if visible_count > 0 && self.cursor >= self.offset + visible_count {
+            self.offset = self.cursor + 1 - visible_count;
+        }
+        let path_width = self.items.iter().map(|(path, _)| path.chars().count()).max().unwrap_or(0);
+        let items: Vec<ListItem> = if self.items.is_empty() {
+            let message = if respect_gitignore {
+                "  Nothing was skipped by .gitignore"
+            } else {
+                "  .gitignore rules are off (g in Filters to turn them on)"
+            };
+            vec![ListItem::new(message).style(Style::default().fg(Color::DarkGray))]
+        } else {
+            self.items
+                .iter()
+                .enumerate()
+                .skip(self.offset)
+                .take(visible_count)
+                .map(|(i, (path, pattern))| {
+                    let prefix = if i == self.cursor { "> " } else { "  " };
+                    let style = if i == self.cursor {
+                        Style::default().fg(Color::LightBlue)
+                    } else {
+                        Style::default().fg(Color::White)
+                    };
+                    ListItem::new(format!("{}{:width$}  {}", prefix, path, pattern, width = path_width))
+                        .style(style)
+                })
+                .collect()
+        };
+        let block = Block::default()
+            .title(format!("Skipped by .gitignore ({}) - path and matching pattern (esc - close)", self.items.len()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::LightBlue));
+        f.render_widget(Clear, area);
+        f.render_widget(List::new(items).block(block), area);
+    }
+
+    pub fn handle_input(&mut self, key: KeyEvent) {
+        let last = self.items.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Up => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.cursor = (self.cursor + 1).min(last);
+            }
+            KeyCode::PageUp => {
+                self.cursor = self.cursor.saturating_sub(10);
+            }
+            KeyCode::PageDown => {
+                self.cursor = (self.cursor + 10).min(last);
+            }
+            KeyCode::Home => {
+                self.cursor = 0;
+            }
+            KeyCode::End => {
+                self.cursor = last;
+            }
+            _ => {}
+        }
+    }
+}
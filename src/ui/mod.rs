@@ -1,23 +1,48 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::Arc;
-use crossterm::event::{KeyCode, KeyEvent};
+use std::time::{Duration, Instant};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Alignment},
+    layout::{Constraint, Direction, Layout, Alignment, Rect},
     style::{Color, Style},
+    text::Line,
     widgets::{Block, Borders, Paragraph, Clear},
     Frame,
 };
-use tokio::sync::mpsc;
-use crate::input::{create_text_source, FilterConfig, SourceFile, TextSource, count_tokens_in_content};
-use crate::output::{write_merged, clipboard::copy_clipboard};
-use crate::ui::output::{OutputDestination, OutputPanel};
-use crate::ui::source_files::{TokenStatus, SourceFilesPanel};
+use notify::RecommendedWatcher;
+use regex::Regex;
+use tokio::sync::{mpsc, Semaphore};
+use crate::input::{create_text_source, is_repository_source, DEFAULT_CONCURRENCY, format_window, MODIFIED_WINDOWS, file_system::get_extension, FilterConfig, SourceFile, SourceType, TextSource, TextSourceError, TokenizerModel, count_tokens_in_content};
+use crate::output::{write_merged, MERGE_BATCH_SIZE, clipboard::copy_clipboard, file::{default_path_prefix, expand_output_template, selection_list_path, validate_output_dir, validate_output_path, write_file_list}, MergeError, MergeOptions, MergeStats, SplitPolicy};
+use crate::ui::debug::{DebugPanel, OperationTimer};
+use crate::input::generated::{is_generated, load_generated_patterns};
+use crate::input::glob::glob_match;
+use crate::output::history::{load_history, record_merge, HistoryEntry};
+use crate::output::pricing::{load_pricing, ModelPrice};
+use crate::ui::extensions::ExtensionsPanel;
+use crate::ui::ignored::IgnoredPanel;
+use crate::ui::spinner::SPINNER_FRAMES;
+use crate::ui::confirm::{set_confirm_skipped, MergeConfirm};
+use crate::ui::filters::{extension_group, ExtensionKind, NO_EXTENSION};
+use crate::ui::history::HistoryPanel;
+use crate::ui::output::{OutputDestination, OutputFormat, OutputPanel};
+use crate::ui::preview::PreviewPanel;
+use crate::ui::source_files::{LONG_LINE_BYTES, format_number, TokenStatus, SourceFilesPanel};
 
 pub mod source_path;
 pub mod filters;
 pub mod source_files;
 pub mod output_file;
 pub mod output;
+pub mod preview;
+pub mod debug;
+pub mod history;
+pub mod extensions;
+pub mod ignored;
+pub mod confirm;
+pub mod spinner;
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum FocusedPanel {
@@ -46,108 +71,216 @@ impl FocusedPanel {
     }
     pub fn prev_panel(&self, app: &App) -> Self {
         match self {
-            FocusedPanel::SourcePath => FocusedPanel::OutputFile,
-            FocusedPanel::Filters => FocusedPanel::SourcePath,
-            FocusedPanel::SourceFiles => FocusedPanel::Filters,
-            FocusedPanel::Output => FocusedPanel::SourceFiles,
-            FocusedPanel::OutputFile => {
+            FocusedPanel::SourcePath => {
                 if app.output_panel.destination == OutputDestination::Clipboard {
                     FocusedPanel::Output
                 } else {
-                    FocusedPanel::Output
+                    FocusedPanel::OutputFile
                 }
             }
+            FocusedPanel::Filters => FocusedPanel::SourcePath,
+            FocusedPanel::SourceFiles => FocusedPanel::Filters,
+            FocusedPanel::Output => FocusedPanel::SourceFiles,
+            FocusedPanel::OutputFile => FocusedPanel::Output,
         }
     }
 }
 
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+const MIN_WIDTH: u16 = 50;
+const MIN_HEIGHT: u16 = 15;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RetryOperation {
+    Reload,
+    Merge,
+}
+
+pub struct TokenCountResult {
+    pub path: String,
+    pub revision: Option<String>,
+    pub model: TokenizerModel,
+    pub result: Result<usize, TextSourceError>,
+    pub long_lines: bool,
+}
+
+pub struct TextOverridePrompt {
+    pub path: String,
+    pub extension: String,
+    pub retry_merge: bool,
+}
+
 pub struct App {
     pub source_path_panel: source_path::SourcePathPanel,
     pub filters_panel: filters::FiltersPanel,
     pub source_files_panel: SourceFilesPanel,
     pub output_panel: OutputPanel,
     pub output_file_panel: output_file::OutputFilePanel,
+    pub preview: Option<PreviewPanel>,
+    pub debug_panel: DebugPanel,
+    pub history: Vec<HistoryEntry>,
+    pub history_panel: Option<HistoryPanel>,
+    pub extensions_panel: Option<ExtensionsPanel>,
+    pub ignored_panel: Option<IgnoredPanel>,
+    pub pending_rerun: Option<HistoryEntry>,
+    pub pending_edit: Option<PathBuf>,
+    pub edited_file: Option<String>,
+    pub pricing: Vec<ModelPrice>,
+    pub last_merge: Option<(HashSet<String>, String)>,
+    pub text_override: Option<TextOverridePrompt>,
     pub focused_panel: FocusedPanel,
     pub loaded_files: Vec<SourceFile>,
     pub selected_extensions: HashSet<String>,
     pub selected_files: HashSet<String>,
+    pub pinned_files: HashSet<String>,
+    pub merge_order: Vec<String>,
+    pub post_process: Option<String>,
+    pub generated_patterns: Vec<String>,
     pub processing: bool,
+    pub progress: Option<String>,
     pub filter_config: FilterConfig,
     pub text_source: Option<Arc<dyn TextSource>>,
     pub exit_requested: bool,
+    pub exit_pending: bool,
     pub reload_files_needed: bool,
     pub merge_needed: bool,
+    pub merge_confirm: Option<MergeConfirm>,
+    pub confirm_merges: bool,
+    pub pending_search: Option<Regex>,
+    pub indexed_source: Option<String>,
     pub prev_source_path: String,
-    pub token_count_tx: mpsc::UnboundedSender<(String, Result<usize, String>)>,
-    pub token_count_rx: mpsc::UnboundedReceiver<(String, Result<usize, String>)>,
+    pub status_message: Option<String>,
+    pub failed_operation: Option<RetryOperation>,
+    pub output_template: String,
+    pub output_path_customized: bool,
+    pub preamble: Option<String>,
+    pub preamble_tokens: usize,
+    pub postamble: Option<String>,
+    pub max_output_bytes: Option<usize>,
+    pub oversize_pending: bool,
+    pub allow_oversize_once: bool,
+    pub count_started: Option<Instant>,
+    pub count_timer: Option<OperationTimer>,
+    pub count_finished: usize,
+    pub tokenizer: TokenizerModel,
+    pub split: Option<SplitPolicy>,
+    pub path_prefix: Option<String>,
+    pub max_file_tokens: Option<usize>,
+    pub token_cache: HashMap<(String, String, TokenizerModel), usize>,
+    pub token_count_tx: mpsc::UnboundedSender<TokenCountResult>,
+    pub count_permits: Arc<Semaphore>,
+    pub token_count_rx: mpsc::UnboundedReceiver<TokenCountResult>,
+    pub watch_enabled: bool,
+    pub watcher: Option<RecommendedWatcher>,
+    pub last_fs_change: Option<Instant>,
+    pub fs_change_tx: mpsc::UnboundedSender<PathBuf>,
+    pub fs_change_rx: mpsc::UnboundedReceiver<PathBuf>,
 }
 
 impl App {
-    pub fn new(default_path: String, default_output_path: String) -> Self {
+    pub fn new(default_path: String) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let (fs_tx, fs_rx) = mpsc::unbounded_channel();
         Self {
             source_path_panel: source_path::SourcePathPanel::new(default_path.clone()),
             filters_panel: filters::FiltersPanel::new(),
             source_files_panel: SourceFilesPanel::new(),
             output_panel: OutputPanel::new(),
-            output_file_panel: output_file::OutputFilePanel::new(default_output_path),
+            output_file_panel: output_file::OutputFilePanel::new(String::new()),
+            preview: None,
+            debug_panel: DebugPanel::default(),
+            history: load_history(),
+            history_panel: None,
+            extensions_panel: None,
+            ignored_panel: None,
+            pending_rerun: None,
+            pending_edit: None,
+            edited_file: None,
+            pricing: load_pricing(),
+            last_merge: None,
+            text_override: None,
             focused_panel: FocusedPanel::SourcePath,
             loaded_files: Vec::new(),
             selected_extensions: HashSet::new(),
             selected_files: HashSet::new(),
+            pinned_files: HashSet::new(),
+            merge_order: Vec::new(),
+            post_process: None,
+            generated_patterns: load_generated_patterns(),
             processing: false,
+            progress: None,
             filter_config: FilterConfig::new(),
             text_source: None,
             exit_requested: false,
+            exit_pending: false,
             reload_files_needed: false,
             merge_needed: false,
+            merge_confirm: None,
+            confirm_merges: true,
+            pending_search: None,
+            indexed_source: None,
             prev_source_path: default_path,
+            status_message: None,
+            failed_operation: None,
+            output_template: crate::cli::DEFAULT_OUTPUT_TEMPLATE.to_string(),
+            output_path_customized: false,
+            preamble: None,
+            preamble_tokens: 0,
+            postamble: None,
+            max_output_bytes: Some(crate::cli::DEFAULT_MAX_OUTPUT_MB * 1024 * 1024),
+            oversize_pending: false,
+            allow_oversize_once: false,
+            count_started: None,
+            count_timer: None,
+            count_finished: 0,
+            tokenizer: TokenizerModel::O200kBase,
+            split: None,
+            path_prefix: None,
+            max_file_tokens: None,
+            token_cache: HashMap::new(),
             token_count_tx: tx,
+            count_permits: Arc::new(Semaphore::new(DEFAULT_CONCURRENCY)),
             token_count_rx: rx,
+            watch_enabled: false,
+            watcher: None,
+            last_fs_change: None,
+            fs_change_tx: fs_tx,
+            fs_change_rx: fs_rx,
         }
     }
 
     pub fn draw(&mut self, f: &mut Frame) {
         self.process_token_count_results();
-        let show_output_file = self.output_panel.destination != OutputDestination::Clipboard;
-        let mut row_constraints = vec![
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(1),
-        ];
-        if !show_output_file {
-            row_constraints[3] = Constraint::Length(0);
+        let area = f.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            draw_too_small(f, area);
+            return;
         }
-        let main_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(row_constraints)
-            .split(f.area());
+        let show_output_file = self.output_panel.destination != OutputDestination::Clipboard;
+        let (main_chunks, mid) = self.panel_areas(f.area());
 
         self.source_path_panel.draw(
             f,
             main_chunks[0],
-            self.focused_panel == FocusedPanel::SourcePath
+            self.focused_panel == FocusedPanel::SourcePath,
+            self.watcher.is_some()
         );
 
-        let mid = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(30), Constraint::Min(10)])
-            .split(main_chunks[1]);
-
         self.filters_panel.draw(
             f,
             mid[0],
             self.focused_panel == FocusedPanel::Filters,
-            &self.selected_extensions
+            &self.selected_extensions,
+            &self.filter_config
         );
 
         self.source_files_panel.draw(
             f,
             mid[1],
             self.focused_panel == FocusedPanel::SourceFiles,
-            &self.selected_files
+            &self.selected_files,
+            &self.pinned_files,
+            &self.merge_positions()
         );
 
         self.output_panel.draw(
@@ -160,55 +293,172 @@ impl App {
             self.output_file_panel.draw(
                 f,
                 main_chunks[3],
-                self.focused_panel == FocusedPanel::OutputFile
+                self.focused_panel == FocusedPanel::OutputFile,
+                self.output_panel.destination == OutputDestination::Directory
             );
         }
 
-        let paragraph = Paragraph::new(self.get_bottom_text())
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray));
+        let paragraph = if let Some(msg) = &self.status_message {
+            Paragraph::new(msg.as_str())
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::Yellow))
+        } else if self.text_override.is_some() {
+            Paragraph::new(self.get_bottom_text())
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::Yellow))
+        } else {
+            Paragraph::new(self.get_bottom_text())
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::DarkGray))
+        };
         f.render_widget(paragraph, main_chunks[4]);
 
+        if let Some(preview) = self.preview.as_mut() {
+            preview.draw(f, preview_area(f.area()));
+        }
+
+        if let Some(history_panel) = self.history_panel.as_mut() {
+            history_panel.draw(f, preview_area(f.area()), &self.history);
+        }
+
+        if let Some(extensions_panel) = self.extensions_panel.as_mut() {
+            extensions_panel.draw(f, preview_area(f.area()), &self.filter_config);
+        }
+
+        if let Some(merge_confirm) = &self.merge_confirm {
+            merge_confirm.draw(f, f.area());
+        }
+
+        if let Some(ignored_panel) = self.ignored_panel.as_mut() {
+            ignored_panel.draw(f, preview_area(f.area()), self.filter_config.respect_gitignore);
+        }
+
+        if self.debug_panel.visible {
+            self.debug_panel.draw(f, f.area(), self.filter_config.concurrency);
+        }
+
         if self.processing {
             self.draw_overlay(f);
         }
     }
 
+    fn panel_areas(&self, area: Rect) -> (Rc<[Rect]>, Rc<[Rect]>) {
+        let mut row_constraints = vec![
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ];
+        if self.output_panel.destination == OutputDestination::Clipboard {
+            row_constraints[3] = Constraint::Length(0);
+        }
+        let main_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(row_constraints)
+            .split(area);
+        let mid = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(30), Constraint::Min(10)])
+            .split(main_chunks[1]);
+        (main_chunks, mid)
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        let area = Rect::new(0, 0, width, height);
+        let (_, mid) = self.panel_areas(area);
+        let custom_lines = [
+            &self.filter_config.additional_text_extensions,
+            &self.filter_config.additional_binary_extensions,
+        ]
+        .iter()
+        .filter(|exts| !exts.is_empty())
+        .count();
+        self.filters_panel.visible_height =
+            (mid[0].height.saturating_sub(2) as usize).saturating_sub(custom_lines);
+        self.filters_panel.ensure_cursor_visible(self.filters_panel.visible_height);
+        self.source_files_panel.visible_height = mid[1].height.saturating_sub(2) as usize;
+        self.source_files_panel.ensure_cursor_visible(self.source_files_panel.visible_height);
+        if let Some(preview) = self.preview.as_mut() {
+            preview.resize(preview_area(area).height.saturating_sub(2) as usize);
+        }
+    }
+
     fn get_bottom_text(&self) -> String {
+        if let Some(prompt) = &self.text_override {
+            return format!(
+                "{} is not valid UTF-8  •  t - treat .{} as text (lossy) & retry  •  esc - skip",
+                prompt.path, prompt.extension
+            );
+        }
+        if self.focused_panel == FocusedPanel::Filters && self.filters_panel.extension_input.is_some() {
+            return "type extensions separated by spaces (listed ones are removed)  •  enter - apply & reload  •  esc - cancel".to_string();
+        }
+        if self.preview.is_some() {
+            return "↑/↓ - scroll  •  PgUp/PgDn - page  •  Home/End - top/bottom  •  esc - close preview".to_string();
+        }
         match self.focused_panel {
             FocusedPanel::SourcePath =>
-                "enter - focus Filters  •  F1 - reload  •  F2 - generate  •  F3 - clear  •  F10/esc - close".to_string(),
+                "enter - focus Filters  •  F1 - reload  •  F2 - generate  •  F3 - clear  •  F4 - watch  •  esc esc/F10 - close".to_string(),
             FocusedPanel::Filters =>
-                "↑/↓ - navigate  •  space - (de)select  •  enter - focus Files  •  esc - focus Source  •  F1 - reload  •  F2 - generate  •  F10 - close".to_string(),
+                "↑/↓ - navigate  •  space - (de)select  •  a - allowlist selected  •  g - gitignore on/off  •  r - drop files of unchecked extensions  •  i - gitignored files  •  w - modified within  •  s - sort by tokens  •  t/b - custom text/binary extensions  •  c - extension classes  •  enter - focus Files  •  esc - focus Source  •  F1 - reload  •  F2 - generate  •  F10 - close".to_string(),
+            FocusedPanel::SourceFiles if self.source_files_panel.search_input.is_some() =>
+                "type a regex  •  enter - search file contents  •  esc - cancel".to_string(),
+            FocusedPanel::SourceFiles if self.source_files_panel.pattern_input.is_some() =>
+                "type a glob, e.g. src/**/*.rs or *.md  •  enter - apply  •  esc - cancel".to_string(),
             FocusedPanel::SourceFiles =>
-                "↑/↓ - navigate  •  space - (de)select  •  enter - count tokens & focus Output  •  esc - focus Filters  •  F1 - reload  •  F2 - generate  •  F10 - close".to_string(),
+                "↑/↓ - navigate  •  space - (de)select  •  e - (de)select extension  •  +/- - min tokens  •  / - search  •  m - select matches  •  g/G - (de)select glob  •  c - copy file  •  y/Y - copy relative/absolute path  •  p - preview  •  o - open in editor  •  i - invert  •  P - pin  •  alt+↑/↓ - merge order  •  = - path order  •  t - type colors  •  $ - cost model  •  x - export list  •  r - recount  •  k - tokenizer  •  enter - count tokens & focus Output  •  esc - focus Filters  •  F1 - reload  •  F2 - generate  •  F10 - close".to_string(),
             FocusedPanel::Output => {
                 match self.output_panel.destination {
                     OutputDestination::File |
-                    OutputDestination::FileAndClipboard =>
-                        "←/→ - toggle  •  enter - focus Output File  •  esc - focus Files  •  F1 - reload  •  F2 - generate  •  F10 - close".to_string(),
+                    OutputDestination::FileAndClipboard |
+                    OutputDestination::Directory =>
+                        "←/→ - toggle  •  f - text/md/json  •  d - dedupe  •  n - notebooks  •  s - summaries  •  h - tree  •  c - contents  •  p - prefix paths  •  r - redact secrets  •  a - all or nothing  •  w - wrap long lines  •  enter - focus Output File  •  esc - focus Files  •  F1 - reload  •  F2 - generate  •  F10 - close".to_string(),
                     OutputDestination::Clipboard =>
-                        "←/→ - toggle  •  enter/F2 - generate  •  esc - focus Files  •  F1 - reload  •  F10 - close".to_string()
+                        "←/→ - toggle  •  f - text/md/json  •  d - dedupe  •  n - notebooks  •  s - summaries  •  h - tree  •  c - contents  •  p - prefix paths  •  r - redact secrets  •  a - all or nothing  •  w - wrap long lines  •  enter/F2 - generate  •  esc - focus Files  •  F1 - reload  •  F10 - close".to_string()
                 }
             }
             FocusedPanel::OutputFile =>
-                "enter/F2 - generate  •  esc - focus Output  •  F1 - reload  •  F3 - clear  •  F10 - close".to_string()
+                "enter/F2 - generate  •  ctrl+r - reset path  •  esc - focus Output  •  F1 - reload  •  F3 - clear  •  F10 - close".to_string()
         }
     }
 
     fn draw_overlay(&self, f: &mut Frame) {
-        let overlay_rect = f.area();
-        let overlay_area = self.centered_rect(30, 5, overlay_rect);
+        let overlay_area = self.overlay_area(f.area());
         let overlay_block = Block::default()
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::White).bg(Color::Black));
         f.render_widget(Clear, overlay_area);
         f.render_widget(overlay_block.clone(), overlay_area);
         let inner = overlay_block.inner(overlay_area);
-        let overlay_text = Paragraph::new("Processing...");
+        let message = self.progress.as_deref().unwrap_or("Processing...");
+        let overlay_text = Paragraph::new(format!("{} {}", SPINNER_FRAMES[0], message));
         f.render_widget(overlay_text, inner);
     }
 
+    fn overlay_area(&self, area: Rect) -> Rect {
+        self.centered_rect(40, 5, area)
+    }
+
+    pub fn spinner_position(&self, area: Rect) -> (u16, u16) {
+        let overlay_area = self.overlay_area(area);
+        (overlay_area.x + 1, overlay_area.y + 1)
+    }
+
+    pub fn begin_progress(&mut self) {
+        self.progress = Some(if self.reload_files_needed {
+            if is_repository_source(&self.source_path_panel.value) {
+                "Fetching repository tree...".to_string()
+            } else {
+                "Indexing files...".to_string()
+            }
+        } else if self.pending_search.is_some() {
+            "Searching file contents...".to_string()
+        } else {
+            format!("Merging {} files...", format_number(self.selected_files.len()))
+        });
+    }
+
     fn centered_rect(&self, width: u16, height: u16, r: ratatui::layout::Rect) -> ratatui::layout::Rect {
         let left = r.x + (r.width.saturating_sub(width)) / 2;
         let top = r.y + (r.height.saturating_sub(height)) / 2;
@@ -221,14 +471,118 @@ impl App {
     }
 
     pub async fn update(&mut self, key_event: KeyEvent) {
+        self.handle_key(key_event).await;
+        self.apply_pinned();
+        if self.last_merge.as_ref().is_some_and(|(selected, _)| *selected != self.selected_files) {
+            self.last_merge = None;
+        }
+    }
+
+    async fn handle_key(&mut self, key_event: KeyEvent) {
         let old_focused_panel = self.focused_panel;
+        self.status_message = None;
+        let confirming_oversize = std::mem::take(&mut self.oversize_pending);
+        let confirming_exit = std::mem::take(&mut self.exit_pending);
+        if let Some(prompt) = self.text_override.take() {
+            match key_event.code {
+                KeyCode::Char('t') => self.apply_text_override(prompt),
+                KeyCode::Esc => {}
+                KeyCode::F(10) => self.exit_requested = true,
+                _ => self.text_override = Some(prompt),
+            }
+            return;
+        }
+        if let Some(extensions_panel) = self.extensions_panel.as_mut() {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Enter => {
+                    if extensions_panel.changed {
+                        self.reload_files_needed = true;
+                    }
+                    self.extensions_panel = None;
+                }
+                KeyCode::F(10) => self.exit_requested = true,
+                _ => extensions_panel.handle_input(key_event, &mut self.filter_config),
+            }
+            return;
+        }
+        if self.merge_confirm.is_some() {
+            match key_event.code {
+                KeyCode::Enter | KeyCode::F(2) => {
+                    self.merge_confirm = None;
+                    self.merge_needed = true;
+                }
+                KeyCode::Char('a') => {
+                    self.merge_confirm = None;
+                    self.confirm_merges = false;
+                    self.merge_needed = true;
+                    if let Err(e) = set_confirm_skipped(true) {
+                        self.status_message = Some(format!("{}  •  not asking again this session", e));
+                    }
+                }
+                KeyCode::Esc => {
+                    self.merge_confirm = None;
+                    self.allow_oversize_once = false;
+                }
+                KeyCode::F(10) => self.exit_requested = true,
+                _ => {}
+            }
+            return;
+        }
+        if let Some(ignored_panel) = self.ignored_panel.as_mut() {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('i') => self.ignored_panel = None,
+                KeyCode::F(10) => self.exit_requested = true,
+                _ => ignored_panel.handle_input(key_event),
+            }
+            return;
+        }
+        if let Some(history_panel) = self.history_panel.as_mut() {
+            match key_event.code {
+                KeyCode::Esc => self.history_panel = None,
+                KeyCode::F(10) => self.exit_requested = true,
+                KeyCode::Enter => {
+                    let cursor = history_panel.cursor;
+                    self.history_panel = None;
+                    if let Some(entry) = self.history.get(cursor).cloned() {
+                        self.rerun_merge(entry);
+                    }
+                }
+                _ => history_panel.handle_input(key_event, self.history.len()),
+            }
+            return;
+        }
+        if let Some(preview) = self.preview.as_mut() {
+            match key_event.code {
+                KeyCode::Esc => self.preview = None,
+                KeyCode::F(10) => self.exit_requested = true,
+                _ => preview.handle_input(key_event),
+            }
+            return;
+        }
+        if self.focused_panel == FocusedPanel::SourceFiles && self.source_files_panel.search_input.is_some() {
+            self.handle_search_input(key_event);
+            return;
+        }
+        if self.focused_panel == FocusedPanel::SourceFiles && self.source_files_panel.pattern_input.is_some() {
+            self.handle_pattern_input(key_event);
+            return;
+        }
+        if self.focused_panel == FocusedPanel::Filters && self.filters_panel.extension_input.is_some() {
+            self.handle_extension_input(key_event);
+            return;
+        }
         match key_event.code {
             KeyCode::F(n) if n == 10 => {
                 self.exit_requested = true;
             }
             KeyCode::Esc => {
                 if self.focused_panel == FocusedPanel::SourcePath {
-                    self.exit_requested = true;
+                    if confirming_exit {
+                        self.exit_requested = true;
+                    } else {
+                        self.exit_pending = true;
+                        self.status_message = Some("Press Esc again to quit (F10 quits immediately)".to_string());
+                    }
                 } else {
                     self.focused_panel = self.focused_panel.prev_panel(self);
                     self.set_cursor_to_end();
@@ -241,7 +595,11 @@ impl App {
             }
             KeyCode::F(n) if n == 2 => {
                 if !self.processing {
-                    self.merge_needed = true;
+                    if confirming_oversize {
+                        self.merge_needed = true;
+                    } else {
+                        self.request_merge();
+                    }
                 }
             }
             KeyCode::F(n) if n == 3 => {
@@ -253,10 +611,53 @@ impl App {
                     FocusedPanel::OutputFile => {
                         self.output_file_panel.value.clear();
                         self.output_file_panel.cursor_pos = 0;
+                        self.output_path_customized = true;
                     }
                     _ => {}
                 }
             }
+            KeyCode::F(7) => match &self.last_merge {
+                Some((_, content)) => {
+                    self.status_message = Some(match copy_clipboard(content.clone()) {
+                        Ok(()) => "Copied the last merge to the clipboard".to_string(),
+                        Err(e) => e,
+                    });
+                }
+                None => {
+                    self.status_message = Some("Nothing to copy  •  merge first (F2)".to_string());
+                }
+            },
+            KeyCode::F(6) => {
+                if self.history.is_empty() {
+                    self.status_message = Some("No merges recorded yet".to_string());
+                } else {
+                    self.history_panel = Some(HistoryPanel::new());
+                }
+            }
+            KeyCode::F(12) => {
+                self.debug_panel.visible = !self.debug_panel.visible;
+            }
+            KeyCode::F(4) => {
+                self.watch_enabled = !self.watch_enabled;
+                self.restart_watcher();
+            }
+            KeyCode::F(5) => {
+                match self.failed_operation.take() {
+                    Some(RetryOperation::Reload) if !self.processing => self.reload_files_needed = true,
+                    Some(RetryOperation::Merge) if !self.processing => self.merge_needed = true,
+                    Some(operation) => self.failed_operation = Some(operation),
+                    None => {}
+                }
+            }
+            KeyCode::Char('o') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.output_panel.cycle_destination();
+                self.refresh_default_output_path();
+                if self.focused_panel == FocusedPanel::OutputFile
+                    && self.output_panel.destination == OutputDestination::Clipboard
+                {
+                    self.focused_panel = FocusedPanel::Output;
+                }
+            }
             KeyCode::Enter => {
                 self.handle_enter().await;
             }
@@ -285,29 +686,526 @@ impl App {
                         self.source_path_panel.handle_input(key_event);
                     }
                     FocusedPanel::Filters => {
-                        self.filters_panel.handle_input(key_event);
+                        match key_event.code {
+                            KeyCode::Char('a') => self.toggle_allowlist_mode(),
+                            KeyCode::Char('g') => self.toggle_gitignore(),
+                            KeyCode::Char('r') => self.reconcile_selection(),
+                            KeyCode::Char('w') => self.cycle_modified_window(),
+                            KeyCode::Char('i') => {
+                                let ignored = self.text_source.as_ref().map(|ts| ts.ignored_paths()).unwrap_or_default();
+                                self.ignored_panel = Some(IgnoredPanel::new(ignored));
+                            }
+                            KeyCode::Char('c') => {
+                                let file_extensions = self.loaded_files.iter().filter_map(|f| get_extension(&f.path));
+                                self.extensions_panel = Some(ExtensionsPanel::new(&self.filter_config, file_extensions));
+                            }
+                            KeyCode::Char('s') => {
+                                self.filters_panel.sort_by_tokens = !self.filters_panel.sort_by_tokens;
+                                self.filters_panel.sort_items();
+                            }
+                            KeyCode::Char('t') => {
+                                self.filters_panel.extension_input = Some((ExtensionKind::Text, String::new()));
+                            }
+                            KeyCode::Char('b') => {
+                                self.filters_panel.extension_input = Some((ExtensionKind::Binary, String::new()));
+                            }
+                            _ => self.filters_panel.handle_input(key_event),
+                        }
                     }
                     FocusedPanel::SourceFiles => {
-                        self.source_files_panel.handle_input(key_event);
+                        match key_event.code {
+                            KeyCode::Up if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                                self.move_in_merge_order(true);
+                            }
+                            KeyCode::Down if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                                self.move_in_merge_order(false);
+                            }
+                            KeyCode::Char('=') => self.reset_merge_order(),
+                            KeyCode::Char('+') => {
+                                self.source_files_panel.increase_min_tokens();
+                                self.deselect_small_files();
+                            }
+                            KeyCode::Char('-') => {
+                                self.source_files_panel.decrease_min_tokens();
+                            }
+                            KeyCode::Char('/') => {
+                                self.source_files_panel.search_input = Some(String::new());
+                            }
+                            KeyCode::Char('g') => {
+                                self.source_files_panel.pattern_input = Some((true, String::new()));
+                            }
+                            KeyCode::Char('G') => {
+                                self.source_files_panel.pattern_input = Some((false, String::new()));
+                            }
+                            KeyCode::Char('m') => {
+                                self.select_search_matches();
+                            }
+                            KeyCode::Char('c') => {
+                                self.copy_file_under_cursor().await;
+                            }
+                            KeyCode::Char('p') => {
+                                self.open_preview().await;
+                            }
+                            KeyCode::Char('i') => {
+                                self.invert_selection();
+                            }
+                            KeyCode::Char('P') => {
+                                self.toggle_pin_under_cursor();
+                            }
+                            KeyCode::Char('x') => {
+                                self.export_selection();
+                            }
+                            KeyCode::Char('e') => {
+                                self.toggle_extension_under_cursor();
+                            }
+                            KeyCode::Char('r') => {
+                                self.recount_tokens();
+                            }
+                            KeyCode::Char('t') => {
+                                self.source_files_panel.color_by_type = !self.source_files_panel.color_by_type;
+                            }
+                            KeyCode::Char('o') => {
+                                self.edit_file_under_cursor();
+                            }
+                            KeyCode::Char('y') => {
+                                self.copy_path_under_cursor(false);
+                            }
+                            KeyCode::Char('Y') => {
+                                self.copy_path_under_cursor(true);
+                            }
+                            KeyCode::Char('$') => {
+                                self.cycle_price_model();
+                            }
+                            KeyCode::Char('k') => {
+                                self.tokenizer = self.tokenizer.next();
+                                self.recount_tokens();
+                                self.status_message = Some(format!("Counting tokens with {}", self.tokenizer.name()));
+                            }
+                            _ => self.source_files_panel.handle_input(key_event),
+                        }
                     }
                     FocusedPanel::Output => {
                         self.output_panel.handle_input(key_event);
+                        self.sync_extra_tokens();
+                        self.refresh_default_output_path();
                     }
                     FocusedPanel::OutputFile => {
-                        self.output_file_panel.handle_input(key_event);
+                        if key_event.code == KeyCode::Char('r')
+                            && key_event.modifiers.contains(KeyModifiers::CONTROL)
+                        {
+                            self.reset_output_path();
+                        } else {
+                            let previous = self.output_file_panel.value.clone();
+                            self.output_file_panel.handle_input(key_event);
+                            if self.output_file_panel.value != previous {
+                                self.output_path_customized = true;
+                            }
+                        }
                     }
                 }
             }
         }
+        if confirming_oversize && self.merge_needed {
+            self.allow_oversize_once = true;
+        }
         let new_focused_panel = self.focused_panel;
         if old_focused_panel == FocusedPanel::SourcePath && new_focused_panel != FocusedPanel::SourcePath {
             if self.source_path_panel.value != self.prev_source_path {
                 self.reload_files_needed = true;
                 self.prev_source_path = self.source_path_panel.value.clone();
+                if !self.output_path_customized {
+                    self.reset_output_path();
+                }
             }
         }
     }
 
+    fn handle_pattern_input(&mut self, key_event: KeyEvent) {
+        let Some((select, pattern)) = self.source_files_panel.pattern_input.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.source_files_panel.pattern_input = None;
+            }
+            KeyCode::Enter => {
+                let (select, pattern) = (*select, std::mem::take(pattern));
+                self.source_files_panel.pattern_input = None;
+                if !pattern.is_empty() {
+                    self.select_by_pattern(&pattern, select);
+                }
+            }
+            KeyCode::Backspace => {
+                pattern.pop();
+            }
+            KeyCode::Char(c) => {
+                pattern.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    fn select_by_pattern(&mut self, pattern: &str, select: bool) {
+        let pattern = pattern.trim().trim_start_matches('/');
+        let matched: Vec<String> = self
+            .source_files_panel
+            .items
+            .iter()
+            .filter(|path| {
+                if pattern.contains('/') {
+                    glob_match(pattern, path)
+                } else {
+                    glob_match(pattern, path.rsplit('/').next().unwrap_or(path))
+                }
+            })
+            .cloned()
+            .collect();
+        for path in &matched {
+            if select {
+                self.selected_files.insert(path.clone());
+            } else {
+                self.selected_files.remove(path);
+            }
+        }
+        self.sync_extension_selection();
+        self.source_files_panel.refresh_title(&self.selected_files);
+        let action = if select { "selected" } else { "deselected" };
+        self.status_message = Some(format!("{} files matched {} and were {}", matched.len(), pattern, action));
+    }
+
+    fn handle_search_input(&mut self, key_event: KeyEvent) {
+        let Some(query) = self.source_files_panel.search_input.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.source_files_panel.search_input = None;
+            }
+            KeyCode::Enter => {
+                let query = self.source_files_panel.search_input.take().unwrap_or_default();
+                if query.is_empty() {
+                    self.source_files_panel.search_matches.clear();
+                    return;
+                }
+                match Regex::new(&query) {
+                    Ok(re) => {
+                        if !self.processing {
+                            self.pending_search = Some(re);
+                        }
+                    }
+                    Err(e) => {
+                        self.status_message = Some(format!("Invalid regex: {}", e));
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_extension_input(&mut self, key_event: KeyEvent) {
+        let Some((kind, value)) = self.filters_panel.extension_input.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.filters_panel.extension_input = None;
+            }
+            KeyCode::Enter => {
+                let kind = *kind;
+                let value = std::mem::take(value);
+                self.filters_panel.extension_input = None;
+                let (target, other) = match kind {
+                    ExtensionKind::Text => (
+                        &mut self.filter_config.additional_text_extensions,
+                        &mut self.filter_config.additional_binary_extensions,
+                    ),
+                    ExtensionKind::Binary => (
+                        &mut self.filter_config.additional_binary_extensions,
+                        &mut self.filter_config.additional_text_extensions,
+                    ),
+                };
+                let mut changed = false;
+                for ext in value.split([' ', ',']).map(|e| e.trim_start_matches('.').to_lowercase()) {
+                    if ext.is_empty() {
+                        continue;
+                    }
+                    if !target.remove(&ext) {
+                        other.remove(&ext);
+                        target.insert(ext);
+                    }
+                    changed = true;
+                }
+                if changed && !self.processing {
+                    self.reload_files_needed = true;
+                }
+            }
+            KeyCode::Backspace => {
+                value.pop();
+            }
+            KeyCode::Char(c) => {
+                value.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    pub async fn search_immediate(&mut self) {
+        let Some(re) = self.pending_search.take() else {
+            return;
+        };
+        let mut matches = HashSet::new();
+        for chunk in self.loaded_files.chunks(MERGE_BATCH_SIZE) {
+            let contents = self.reload_file_contents(chunk).await;
+            for (f, content) in chunk.iter().zip(contents) {
+                if content.is_ok_and(|content| re.is_match(&content)) {
+                    matches.insert(f.path.clone());
+                }
+            }
+        }
+        self.status_message = Some(format!(
+            "{} of {} files match /{}/",
+            matches.len(),
+            self.loaded_files.len(),
+            re.as_str()
+        ));
+        self.source_files_panel.search_matches = matches;
+    }
+
+    fn file_under_cursor(&self) -> Option<SourceFile> {
+        let path = self.source_files_panel.items.get(self.source_files_panel.cursor)?;
+        self.loaded_files.iter().find(|f| f.path == *path).cloned()
+    }
+
+    fn edit_file_under_cursor(&mut self) {
+        let Some(sf) = self.file_under_cursor() else {
+            return;
+        };
+        match &sf.source_type {
+            SourceType::FileSystem { base_path, relative_path } => {
+                self.pending_edit = Some(base_path.join(relative_path));
+                self.edited_file = Some(sf.path);
+            }
+            _ => {
+                self.status_message = Some("Only local files can be opened in an editor".to_string());
+            }
+        }
+    }
+
+    pub fn finish_edit(&mut self, result: Result<(), String>) {
+        match result {
+            Ok(()) => {
+                if let Some(path) = &self.edited_file {
+                    self.source_files_panel.file_token_status.insert(path.clone(), TokenStatus::NotCounted);
+                }
+                self.reload_files_needed = true;
+            }
+            Err(e) => {
+                self.edited_file = None;
+                self.status_message = Some(e);
+            }
+        }
+    }
+
+    fn copy_path_under_cursor(&mut self, absolute: bool) {
+        let Some(sf) = self.file_under_cursor() else {
+            return;
+        };
+        let path = if absolute {
+            match &sf.source_type {
+                SourceType::FileSystem { base_path, relative_path } => {
+                    Some(base_path.join(relative_path).to_string_lossy().to_string())
+                }
+                _ => self.web_url(&sf),
+            }
+        } else {
+            Some(sf.path.clone())
+        };
+        let Some(path) = path else {
+            self.status_message = Some(format!("No absolute path for {}", sf.path));
+            return;
+        };
+        self.status_message = Some(match copy_clipboard(path.clone()) {
+            Ok(()) => format!("Copied {} to clipboard", path),
+            Err(e) => format!("Could not copy path: {}", e),
+        });
+    }
+
+    async fn copy_file_under_cursor(&mut self) {
+        let Some(sf) = self.file_under_cursor() else {
+            return;
+        };
+        let result = match self.reload_file_content(&sf).await {
+            Ok(content) => {
+                let len = content.len();
+                copy_clipboard(content).map(|_| len)
+            }
+            Err(e) => {
+                self.offer_text_override(&e, false);
+                Err(e.to_string())
+            }
+        };
+        self.status_message = Some(match result {
+            Ok(len) => format!("Copied {} ({} bytes) to clipboard", sf.path, format_number(len)),
+            Err(e) => format!("Could not copy {}: {}", sf.path, e),
+        });
+    }
+
+    async fn open_preview(&mut self) {
+        let Some(sf) = self.file_under_cursor() else {
+            return;
+        };
+        match self.reload_file_content(&sf).await {
+            Ok(content) => {
+                self.preview = Some(PreviewPanel::new(sf.path, &content));
+            }
+            Err(e) => {
+                self.offer_text_override(&e, false);
+                self.status_message = Some(format!("Could not preview {}: {}", sf.path, e));
+            }
+        }
+    }
+
+    fn invert_selection(&mut self) {
+        self.selected_files = self
+            .loaded_files
+            .iter()
+            .filter(|f| !self.selected_files.contains(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+        self.sync_extension_selection();
+        self.source_files_panel.refresh_title(&self.selected_files);
+    }
+
+    fn toggle_extension_under_cursor(&mut self) {
+        let Some(path) = self.source_files_panel.items.get(self.source_files_panel.cursor) else {
+            return;
+        };
+        let ext = extension_group(path);
+        self.filters_panel.toggle_extension(
+            &ext,
+            &mut self.selected_extensions,
+            &mut self.selected_files,
+            &self.loaded_files
+        );
+        self.source_files_panel.refresh_title(&self.selected_files);
+    }
+
+    fn export_selection(&mut self) {
+        let mut paths: Vec<String> = self
+            .loaded_files
+            .iter()
+            .filter(|f| self.selected_files.contains(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+        paths.sort();
+        let list_path = selection_list_path(&self.output_file_panel.value);
+        self.status_message = Some(match write_file_list(&list_path, &paths) {
+            Ok(()) => format!("Saved {} paths to {}", paths.len(), list_path),
+            Err(e) => e,
+        });
+    }
+
+    fn select_search_matches(&mut self) {
+        if self.source_files_panel.search_matches.is_empty() {
+            return;
+        }
+        self.selected_files = self.source_files_panel.search_matches.clone();
+        self.sync_extension_selection();
+    }
+
+    fn ordered_selection(&self) -> Vec<String> {
+        let positions = self.merge_positions();
+        let mut paths: Vec<String> = self.selected_files.iter().cloned().collect();
+        paths.sort_by(|a, b| {
+            let rank = |path: &String| positions.get(path).copied().unwrap_or(usize::MAX);
+            rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+        });
+        paths
+    }
+
+    fn merge_positions(&self) -> HashMap<String, usize> {
+        self.merge_order
+            .iter()
+            .filter(|p| self.selected_files.contains(*p))
+            .enumerate()
+            .map(|(i, p)| (p.clone(), i + 1))
+            .collect()
+    }
+
+    fn move_in_merge_order(&mut self, up: bool) {
+        let Some(path) = self.source_files_panel.items.get(self.source_files_panel.cursor).cloned() else {
+            return;
+        };
+        if !self.selected_files.contains(&path) {
+            self.status_message = Some("Select the file to change its merge position".to_string());
+            return;
+        }
+        let mut order = self.ordered_selection();
+        let Some(index) = order.iter().position(|p| *p == path) else {
+            return;
+        };
+        let target = if up {
+            index.saturating_sub(1)
+        } else {
+            (index + 1).min(order.len() - 1)
+        };
+        order.swap(index, target);
+        self.merge_order = order;
+        self.last_merge = None;
+        self.status_message = Some(format!("{} is merged at position {} of {}", path, target + 1, self.merge_order.len()));
+    }
+
+    fn reset_merge_order(&mut self) {
+        self.merge_order.clear();
+        self.last_merge = None;
+        self.status_message = Some("Merging selected files in path order".to_string());
+    }
+
+    fn reconcile_selection(&mut self) {
+        let before = self.selected_files.len();
+        let selected_extensions = &self.selected_extensions;
+        let pinned = &self.pinned_files;
+        self.selected_files.retain(|path| {
+            pinned.contains(path)
+                || selected_extensions.contains(&extension_group(path))
+        });
+        let dropped = before - self.selected_files.len();
+        self.sync_extension_selection();
+        self.source_files_panel.refresh_title(&self.selected_files);
+        self.status_message = Some(if dropped == 0 {
+            "Selection already matches the checked extensions".to_string()
+        } else {
+            format!("Deselected {} files whose extension is unchecked", dropped)
+        });
+    }
+
+    fn sync_extension_selection(&mut self) {
+        self.selected_extensions.clear();
+        for item in self.filters_panel.items.iter().filter(|it| it.as_str() != "*") {
+            let all_selected = self
+                .loaded_files
+                .iter()
+                .filter(|f| extension_group(&f.path) == *item)
+                .all(|f| self.selected_files.contains(&f.path));
+            if all_selected {
+                self.selected_extensions.insert(item.clone());
+            }
+        }
+        let all_files_selected = self
+            .loaded_files
+            .iter()
+            .all(|f| self.selected_files.contains(&f.path));
+        if all_files_selected {
+            self.selected_extensions.insert("*".to_string());
+        }
+    }
+
     async fn handle_enter(&mut self) {
         match self.focused_panel {
             FocusedPanel::SourceFiles => {
@@ -315,8 +1213,8 @@ impl App {
                 self.focused_panel = self.focused_panel.next_panel(self);
 
                 // Steps 2 & 3: update Files panel title to "counting" & set items to "..."
-                self.source_files_panel.update_title_counting();
                 self.start_token_count_for_selected_files();
+                self.source_files_panel.update_title_counting(&self.selected_files);
 
                 // user can continue interacting with the UI now
                 self.set_cursor_to_end();
@@ -324,16 +1222,16 @@ impl App {
             FocusedPanel::Output => {
                 if self.output_panel.destination == OutputDestination::Clipboard {
                     if !self.processing {
-                        self.merge_needed = true;
+                        self.request_merge();
                     }
                 } else {
-                    self.focused_panel = self.focused_panel.next_panel(self);
+                    self.focused_panel = FocusedPanel::OutputFile;
                     self.set_cursor_to_end();
                 }
             }
             FocusedPanel::OutputFile => {
                 if !self.processing {
-                    self.merge_needed = true;
+                    self.request_merge();
                 }
             }
             _ => {
@@ -344,22 +1242,91 @@ impl App {
     }
 
     pub async fn reload_files_immediate(&mut self) {
+        self.reload_index().await;
+        self.refresh_extension_weights();
+        self.apply_pinned();
+        if let Some(entry) = self.pending_rerun.take() {
+            self.restore_selection(&entry);
+        }
+        if let Some(path) = self.edited_file.take() {
+            if self.selected_files.contains(&path) {
+                self.start_token_count(&[path]);
+                self.source_files_panel.update_title_counting(&self.selected_files);
+            }
+        }
+    }
+
+    async fn reload_index(&mut self) {
         self.reload_files_needed = false;
+        self.last_merge = None;
+        self.failed_operation = None;
+        self.filter_config.exclude_file = self.output_target().map(|p| PathBuf::from(crate::config::expand_path(&p)));
         let path = self.source_path_panel.value.clone();
+        let previous_files = std::mem::take(&mut self.loaded_files);
+        let incremental = self.indexed_source.as_deref() == Some(path.as_str());
+        let timer = OperationTimer::start();
+        let mut indexed = false;
         let ts_result = create_text_source(&path).await;
         if let Ok(ts) = ts_result {
             self.text_source = Some(Arc::from(ts));
             if let Some(ts2) = &self.text_source {
                 let index_res = ts2.get_file_index(&self.filter_config).await;
                 match index_res {
-                    Ok(files) => self.loaded_files = files,
-                    Err(_) => self.loaded_files.clear(),
+                    Ok(files) => {
+                        self.loaded_files = files;
+                        indexed = true;
+                        self.report_submodules();
+                        self.report_index_warnings();
+                    }
+                    Err(e) => {
+                        self.loaded_files.clear();
+                        self.report_failure(RetryOperation::Reload, &e);
+                    }
                 }
             }
         } else {
             self.text_source = None;
             self.loaded_files.clear();
         }
+        self.debug_panel.index = Some(timer.finish(self.loaded_files.len()));
+        if let Some(list) = &self.filter_config.file_list {
+            let missing = list.len().saturating_sub(self.loaded_files.len());
+            if missing > 0 {
+                self.status_message = Some(format!("{} listed files were not found", missing));
+            }
+        }
+        self.indexed_source = indexed.then_some(path);
+        if !incremental {
+            self.restart_watcher();
+        }
+        if incremental && indexed {
+            self.filters_panel.refresh_items(&self.loaded_files);
+            self.source_files_panel.refresh_values(
+                &self.loaded_files,
+                &previous_files,
+                &mut self.selected_files
+            );
+            if self.filter_config.file_list.is_none() {
+                let previous: HashSet<&str> = previous_files.iter().map(|f| f.path.as_str()).collect();
+                for file in &self.loaded_files {
+                    let path = file.path.as_str();
+                    if !previous.contains(path)
+                        && !self.pinned_files.contains(path)
+                        && is_generated(path, &self.generated_patterns)
+                    {
+                        self.selected_files.remove(path);
+                    }
+                }
+            }
+            self.sync_extension_selection();
+            self.report_index_changes(&previous_files);
+            self.report_repository_index();
+            return;
+        }
+        self.selected_extensions.clear();
+        self.selected_files.clear();
+        self.merge_order.clear();
+        self.source_files_panel.file_token_status.clear();
         self.filters_panel.init_values(
             &self.loaded_files,
             &mut self.selected_extensions,
@@ -369,26 +1336,460 @@ impl App {
             &self.loaded_files,
             &mut self.selected_files
         );
+        if self.filter_config.file_list.is_none() {
+            let patterns = &self.generated_patterns;
+            self.selected_files.retain(|p| !is_generated(p, patterns));
+            if self.selected_files.len() < self.loaded_files.len() {
+                self.sync_extension_selection();
+            }
+        }
+        self.report_repository_index();
+    }
+
+    fn report_repository_index(&mut self) {
+        let Some(source) = &self.indexed_source else {
+            return;
+        };
+        if self.status_message.is_none() && is_repository_source(source) {
+            self.status_message = Some(format!(
+                "Indexed {} files from the repository tree",
+                format_number(self.loaded_files.len())
+            ));
+        }
+    }
+
+    fn report_index_warnings(&mut self) {
+        let Some(ts) = &self.text_source else {
+            return;
+        };
+        for warning in ts.index_warnings() {
+            self.status_message = Some(match self.status_message.take() {
+                Some(message) => format!("{}  •  {}", warning, message),
+                None => warning,
+            });
+        }
+    }
+
+    fn report_submodules(&mut self) {
+        let Some(ts) = &self.text_source else {
+            return;
+        };
+        let skipped: Vec<String> = ts
+            .submodules()
+            .into_iter()
+            .filter(|s| !s.included)
+            .map(|s| match s.repository {
+                Some(repository) => format!("{} ({})", s.path, repository),
+                None => s.path,
+            })
+            .collect();
+        if skipped.is_empty() {
+            return;
+        }
+        let mut listed = skipped.iter().take(3).cloned().collect::<Vec<_>>().join(", ");
+        if skipped.len() > 3 {
+            listed.push_str(", …");
+        }
+        let hint = if self.filter_config.include_submodules {
+            "not found or not on this host"
+        } else {
+            "start with --submodules to include them"
+        };
+        self.status_message = Some(format!(
+            "{} submodules not included: {}  •  {}",
+            skipped.len(),
+            listed,
+            hint
+        ));
+    }
+
+    fn output_target(&self) -> Option<String> {
+        let value = &self.output_file_panel.value;
+        match self.output_panel.destination {
+            OutputDestination::Clipboard => None,
+            OutputDestination::Directory => Some(value.clone()),
+            OutputDestination::File | OutputDestination::FileAndClipboard => Some(
+                validate_output_path(value, self.output_panel.format.default_extension())
+                    .unwrap_or_else(|_| value.clone()),
+            ),
+        }
+    }
+
+    fn report_index_changes(&mut self, previous_files: &[SourceFile]) {
+        let previous: HashSet<&str> = previous_files.iter().map(|f| f.path.as_str()).collect();
+        let current: HashSet<&str> = self.loaded_files.iter().map(|f| f.path.as_str()).collect();
+        let added: HashSet<String> = current
+            .difference(&previous)
+            .map(|p| p.to_string())
+            .collect();
+        let removed = previous.difference(&current).count();
+        let selected = added.iter().filter(|p| self.selected_files.contains(*p)).count();
+        if !added.is_empty() || removed > 0 {
+            let mut message = format!(
+                "{} files added, {} removed since last reload",
+                format_number(added.len()),
+                format_number(removed)
+            );
+            if !added.is_empty() {
+                message = format!("{}  •  {} new files selected, marked +", message, format_number(selected));
+            }
+            if let Some(existing) = self.status_message.take() {
+                message = format!("{}  •  {}", message, existing);
+            }
+            self.status_message = Some(message);
+        }
+        self.source_files_panel.added_files = added;
+    }
+
+    fn rerun_merge(&mut self, entry: HistoryEntry) {
+        self.source_path_panel.value = entry.source.clone();
+        self.source_path_panel.cursor_pos = self.source_path_panel.value.len();
+        self.prev_source_path = entry.source.clone();
+        self.output_file_panel.value = entry.output_file.clone();
+        self.output_file_panel.cursor_pos = self.output_file_panel.value.len();
+        self.output_path_customized = true;
+        self.output_panel.format = entry.format.clone();
+        self.sync_extra_tokens();
+        if let Some(index) = self.output_panel.items.iter().position(|d| *d == entry.destination) {
+            self.output_panel.selected = index;
+            self.output_panel.destination = entry.destination.clone();
+        }
+        self.pending_rerun = Some(entry);
+        self.reload_files_needed = true;
+    }
+
+    fn restore_selection(&mut self, entry: &HistoryEntry) {
+        if self.text_source.is_none() {
+            return;
+        }
+        let available: HashSet<&String> = self.source_files_panel.items.iter().collect();
+        self.selected_files = entry
+            .selected
+            .iter()
+            .filter(|p| available.contains(p))
+            .cloned()
+            .collect();
+        self.merge_order = entry
+            .selected
+            .iter()
+            .filter(|p| self.selected_files.contains(*p))
+            .cloned()
+            .collect();
+        let missing = entry.selected.len() - self.selected_files.len();
+        self.sync_extension_selection();
+        self.apply_pinned();
+        self.source_files_panel.refresh_title(&self.selected_files);
+        if missing > 0 {
+            self.status_message = Some(format!("{} files of the previous merge no longer exist", missing));
+        }
+        self.merge_needed = true;
+    }
+
+    pub fn reset_output_path(&mut self) {
+        let source = self.source_path_panel.value.clone();
+        let mut path = std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(expand_output_template(&self.output_template, &source));
+        if self.output_panel.destination == OutputDestination::Directory {
+            path.set_extension("");
+        }
+        self.output_file_panel.value = path.to_string_lossy().to_string();
+        self.output_file_panel.cursor_pos = self.output_file_panel.value.len();
+        self.output_path_customized = false;
+    }
+
+    fn refresh_default_output_path(&mut self) {
+        if self.output_path_customized {
+            return;
+        }
+        let is_directory = self.output_panel.destination == OutputDestination::Directory;
+        let has_extension = std::path::Path::new(&self.output_file_panel.value).extension().is_some();
+        if is_directory == has_extension {
+            self.reset_output_path();
+        }
+    }
+
+    fn cycle_price_model(&mut self) {
+        let next = match &self.source_files_panel.price {
+            Some(current) => self
+                .pricing
+                .iter()
+                .position(|p| p.name == current.name)
+                .map(|i| i + 1)
+                .unwrap_or(0),
+            None => 0,
+        };
+        self.source_files_panel.price = self.pricing.get(next).cloned();
+        self.status_message = Some(match &self.source_files_panel.price {
+            Some(price) => format!("Estimating cost for {} at ${:.2} per 1M input tokens", price.name, price.usd_per_mtok),
+            None => "Cost estimate off".to_string(),
+        });
+        self.source_files_panel.refresh_title(&self.selected_files);
+    }
+
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.filter_config.concurrency = concurrency;
+        self.count_permits = Arc::new(Semaphore::new(concurrency));
+    }
+
+    pub fn set_preamble(&mut self, preamble: Option<String>, postamble: Option<String>) {
+        self.preamble_tokens = [&preamble, &postamble]
+            .iter()
+            .filter_map(|text| text.as_deref())
+            .filter_map(|text| count_tokens_in_content(text, self.tokenizer).ok())
+            .sum();
+        self.preamble = preamble;
+        self.postamble = postamble;
+        self.sync_extra_tokens();
+    }
+
+    fn sync_extra_tokens(&mut self) {
+        self.source_files_panel.extra_tokens = if self.output_panel.format == OutputFormat::Json {
+            0
+        } else {
+            self.preamble_tokens
+        };
+    }
+
+    fn restart_watcher(&mut self) {
+        self.watcher = None;
+        self.last_fs_change = None;
+        if !self.watch_enabled {
+            return;
+        }
+        let Some(ts) = &self.text_source else {
+            return;
+        };
+        match ts.watch(self.fs_change_tx.clone()) {
+            Ok(Some(watcher)) => {
+                self.watcher = Some(watcher);
+            }
+            Ok(None) => {
+                self.watch_enabled = false;
+                self.status_message = Some("Watching is only supported for local directories".to_string());
+            }
+            Err(e) => {
+                self.watch_enabled = false;
+                self.status_message = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn process_fs_changes(&mut self) {
+        if self.watcher.is_some() && !self.fs_change_rx.is_empty() {
+            self.filter_config.exclude_file = self.output_target().map(|p| PathBuf::from(crate::config::expand_path(&p)));
+        }
+        while let Ok(path) = self.fs_change_rx.try_recv() {
+            let relevant = self
+                .text_source
+                .as_ref()
+                .is_some_and(|ts| ts.affects_index(&path, &self.filter_config));
+            if self.watcher.is_some() && relevant {
+                self.last_fs_change = Some(Instant::now());
+            }
+        }
+        if let Some(changed_at) = self.last_fs_change {
+            if changed_at.elapsed() >= WATCH_DEBOUNCE && !self.processing {
+                self.last_fs_change = None;
+                self.reload_files_needed = true;
+            }
+        }
+    }
+
+    fn toggle_allowlist_mode(&mut self) {
+        if self.filter_config.is_allowlist_mode() {
+            self.filter_config.allowlist_extensions = None;
+        } else {
+            let allowlist = self
+                .selected_extensions
+                .iter()
+                .filter(|e| e.as_str() != "*" && e.as_str() != NO_EXTENSION)
+                .map(|e| e.to_lowercase())
+                .collect();
+            self.filter_config.allowlist_extensions = Some(allowlist);
+            self.filter_config.allowlist_no_extension = self.selected_extensions.contains(NO_EXTENSION);
+        }
+        if !self.processing {
+            self.reload_files_needed = true;
+        }
+    }
+
+    fn cycle_modified_window(&mut self) {
+        let current = self.filter_config.modified_within.map(|w| w.as_secs());
+        let next = match current {
+            None => MODIFIED_WINDOWS.first().copied(),
+            Some(secs) => MODIFIED_WINDOWS.iter().copied().find(|w| *w > secs),
+        };
+        self.filter_config.modified_within = next.map(Duration::from_secs);
+        self.status_message = Some(match self.filter_config.modified_within {
+            Some(window) => format!("Listing local files modified within {}", format_window(window)),
+            None => "Listing files regardless of modification time".to_string(),
+        });
+        if !self.processing {
+            self.reload_files_needed = true;
+        }
+    }
+
+    fn toggle_gitignore(&mut self) {
+        self.filter_config.respect_gitignore = !self.filter_config.respect_gitignore;
+        if !self.processing {
+            self.reload_files_needed = true;
+        }
+    }
+
+    fn request_merge(&mut self) {
+        if !self.confirm_merges {
+            self.merge_needed = true;
+            return;
+        }
+        let selected = self.loaded_files.iter().filter(|f| self.selected_files.contains(&f.path));
+        let mut tokens = 0;
+        let mut uncounted = 0;
+        let mut files = 0;
+        for file in selected {
+            files += 1;
+            match self.source_files_panel.known_token_count(&file.path) {
+                Some(n) => tokens += n,
+                None => uncounted += 1,
+            }
+        }
+        let destination = &self.output_panel.destination;
+        self.merge_confirm = Some(MergeConfirm {
+            files,
+            tokens,
+            uncounted,
+            destination: destination.label().to_string(),
+            output_path: self.output_target(),
+            format: self.output_panel.describe(),
+        });
     }
 
     pub async fn merge_immediate(&mut self) {
         self.merge_needed = false;
+        self.failed_operation = None;
         let mut files_map = HashMap::new();
         for f in &self.loaded_files {
             if self.selected_files.contains(&f.path) {
                 files_map.insert(f.path.clone(), f.clone());
             }
         }
-        let output_file = self.output_file_panel.value.clone();
         let dest = self.output_panel.destination.clone();
-        let contents = write_merged(&dest, &output_file, files_map, self).await;
-        if let Ok(merged) = contents {
-            if matches!(dest, OutputDestination::FileAndClipboard) {
-                let _ = copy_clipboard(merged);
+        let options = MergeOptions {
+            format: self.output_panel.format.clone(),
+            deduplicate: self.output_panel.deduplicate,
+            strip_notebooks: self.output_panel.strip_notebooks,
+            preamble: self.preamble.clone(),
+            postamble: self.postamble.clone(),
+            file_summaries: self.output_panel.file_summaries,
+            tree_style: self.output_panel.tree_header.then_some(self.output_panel.tree_style),
+            split: self.split,
+            path_prefix: if self.output_panel.prefix_paths {
+                self.path_prefix
+                    .clone()
+                    .or_else(|| default_path_prefix(&self.source_path_panel.value))
+            } else {
+                None
+            },
+            skip_empty: self.filter_config.skip_empty,
+            max_file_tokens: self.max_file_tokens,
+            order: self.ordered_selection(),
+            table_of_contents: self.output_panel.table_of_contents,
+            post_process: self.post_process.clone(),
+            redact_secrets: self.output_panel.redact_secrets,
+            wrap_column: self.output_panel.wrap_lines.then_some(self.output_panel.wrap_column),
+            all_or_nothing: self.output_panel.all_or_nothing,
+            max_output_bytes: if std::mem::take(&mut self.allow_oversize_once) {
+                None
+            } else {
+                self.max_output_bytes
+            },
+        };
+        if dest == OutputDestination::Directory {
+            if let Err(e) = validate_output_dir(&self.output_file_panel.value) {
+                self.status_message = Some(e);
+                return;
+            }
+        } else if dest != OutputDestination::Clipboard {
+            match validate_output_path(&self.output_file_panel.value, options.format.default_extension()) {
+                Ok(path) => {
+                    self.output_file_panel.value = path;
+                    self.output_file_panel.cursor_pos = self.output_file_panel.value.len();
+                }
+                Err(e) => {
+                    self.status_message = Some(e);
+                    return;
+                }
+            }
+        }
+        let output_file = self.output_file_panel.value.clone();
+        let timer = OperationTimer::start();
+        let contents = write_merged(&dest, &options, &output_file, files_map, self).await;
+        let merged_files = contents.as_ref().map(|o| o.stats.files).unwrap_or(0);
+        self.debug_panel.merge = Some(timer.finish(merged_files));
+        match contents {
+            Ok(output) => {
+                let mut summary = merge_summary(&output.stats);
+                if output.parts > 1 && dest != OutputDestination::Clipboard {
+                    summary = format!("Split into {} parts  •  {}", output.parts, summary);
+                }
+                if options.redact_secrets {
+                    summary = format!("{} secrets redacted  •  {}", output.redactions, summary);
+                }
+                for warning in &output.warnings {
+                    summary = format!("{}  •  {}", warning, summary);
+                }
+                if output.omitted > 0 {
+                    summary = format!("{} files over the token cap left out  •  {}", output.omitted, summary);
+                }
+                if dest == OutputDestination::Directory {
+                    summary = format!("Wrote files to {}  •  {}", output_file, summary);
+                } else if let Some(command) = &self.post_process {
+                    summary = format!("Post-processed with `{}`  •  {}", command, summary);
+                }
+                self.status_message = Some(summary);
+                let entry = HistoryEntry {
+                    source: self.source_path_panel.value.clone(),
+                    selected: self.ordered_selection(),
+                    output_file,
+                    destination: dest.clone(),
+                    format: self.output_panel.format.clone(),
+                    timestamp: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0),
+                };
+                if let Err(e) = record_merge(&mut self.history, entry) {
+                    log::warn!("Could not save merge history: {}", e);
+                }
+                if dest != OutputDestination::Directory {
+                    self.last_merge = Some((self.selected_files.clone(), output.content));
+                }
+            }
+            Err(MergeError::TooLarge { limit }) => {
+                self.oversize_pending = true;
+                self.status_message = Some(format!(
+                    "Output is larger than {} MB  •  press F2 again to merge anyway",
+                    format_number(limit / (1024 * 1024))
+                ));
+            }
+            Err(MergeError::Source(e)) => {
+                self.report_failure(RetryOperation::Merge, &e);
+            }
+            Err(e) => {
+                self.status_message = Some(e.to_string());
             }
         }
     }
 
+    fn report_failure(&mut self, operation: RetryOperation, error: &TextSourceError) {
+        let action = match operation {
+            RetryOperation::Reload => "Could not load source",
+            RetryOperation::Merge => "Merge failed",
+        };
+        self.failed_operation = Some(operation);
+        self.status_message = Some(format!("{}: {}  •  F5 - retry", action, error));
+    }
+
     fn set_cursor_to_end(&mut self) {
         match self.focused_panel {
             FocusedPanel::SourcePath => {
@@ -401,43 +1802,138 @@ impl App {
         }
     }
 
-    pub async fn reload_file_content(&self, sf: &SourceFile) -> Result<String, String> {
-        if let Some(ts) = &self.text_source {
-            match ts.get_file_content(sf).await {
-                Ok(c) => Ok(c),
-                Err(e) => Err(e.to_string()),
+    pub async fn reload_file_content(&self, sf: &SourceFile) -> Result<String, TextSourceError> {
+        match &self.text_source {
+            Some(ts) => ts.get_file_content(sf, &self.filter_config).await,
+            None => Err(TextSourceError::InvalidSource),
+        }
+    }
+
+    pub async fn reload_file_contents(&self, files: &[SourceFile]) -> Vec<Result<String, TextSourceError>> {
+        match &self.text_source {
+            Some(ts) => ts.get_file_contents(files, &self.filter_config).await,
+            None => files.iter().map(|_| Err(TextSourceError::InvalidSource)).collect(),
+        }
+    }
+
+    pub fn web_url(&self, file: &SourceFile) -> Option<String> {
+        self.text_source.as_ref()?.web_url(file)
+    }
+
+    pub fn offer_text_override(&mut self, error: &TextSourceError, retry_merge: bool) {
+        let TextSourceError::NotTextFile(path) = error else {
+            return;
+        };
+        if let Some(prompt) = self.text_override.as_mut() {
+            prompt.retry_merge |= retry_merge;
+            return;
+        }
+        if let Some(extension) = get_extension(path) {
+            self.text_override = Some(TextOverridePrompt {
+                path: path.clone(),
+                extension,
+                retry_merge,
+            });
+        }
+    }
+
+    fn apply_text_override(&mut self, prompt: TextOverridePrompt) {
+        self.filter_config.additional_binary_extensions.remove(&prompt.extension);
+        self.filter_config.additional_text_extensions.insert(prompt.extension.clone());
+        let mut recount = false;
+        for (path, status) in self.source_files_panel.file_token_status.iter_mut() {
+            if matches!(status, TokenStatus::Error)
+                && get_extension(path).as_deref() == Some(prompt.extension.as_str())
+            {
+                *status = TokenStatus::NotCounted;
+                recount = true;
             }
+        }
+        if recount {
+            self.start_token_count_for_selected_files();
+        }
+        if prompt.retry_merge && !self.processing {
+            self.merge_needed = true;
+        }
+        self.status_message = Some(format!("Treating .{} files as text", prompt.extension));
+    }
+
+    fn refresh_extension_weights(&mut self) {
+        self.filters_panel.update_weights(&self.loaded_files, &self.source_files_panel.file_token_status);
+    }
+
+    fn recount_tokens(&mut self) {
+        for f in &self.loaded_files {
+            let cached = f
+                .revision
+                .as_ref()
+                .and_then(|rev| self.token_cache.get(&(f.path.clone(), rev.clone(), self.tokenizer)));
+            let status = match cached {
+                Some(n) => TokenStatus::Done(*n),
+                None => TokenStatus::NotCounted,
+            };
+            self.source_files_panel.file_token_status.insert(f.path.clone(), status);
+        }
+        let (preamble, postamble) = (self.preamble.take(), self.postamble.take());
+        self.set_preamble(preamble, postamble);
+        self.count_finished = 0;
+        self.refresh_extension_weights();
+        self.start_token_count_for_selected_files();
+        if self.source_files_panel.is_counting() {
+            self.source_files_panel.update_title_counting(&self.selected_files);
         } else {
-            Err("No text source available".to_string())
+            self.source_files_panel.refresh_title(&self.selected_files);
         }
     }
 
     fn start_token_count_for_selected_files(&mut self) {
+        let paths: Vec<String> = self.selected_files.iter().cloned().collect();
+        self.start_token_count(&paths);
+    }
+
+    fn start_token_count(&mut self, paths: &[String]) {
         if self.text_source.is_none() {
             return;
         }
         let ts_arc = Arc::clone(self.text_source.as_ref().unwrap());
-        for path in &self.selected_files {
+        let filter = Arc::new(self.filter_config.clone());
+        let model = self.tokenizer;
+        for path in paths {
             if let Some(TokenStatus::NotCounted) = self.source_files_panel.file_token_status.get(path) {
                 self.source_files_panel.set_counting(path);
+                self.count_started.get_or_insert_with(Instant::now);
+                self.count_timer.get_or_insert_with(OperationTimer::start);
                 let p = path.clone();
                 let sf = self.loaded_files.iter().find(|f| f.path == *path).cloned();
                 if let Some(sf2) = sf {
                     let tx = self.token_count_tx.clone();
                     let ts_for_async = Arc::clone(&ts_arc);
+                    let filter = Arc::clone(&filter);
+                    let permits = Arc::clone(&self.count_permits);
                     tokio::spawn(async move {
+                        let _permit = permits.acquire_owned().await;
                         log::info!("Starting token count for {}", p);
-                        let content_res = ts_for_async.get_file_content(&sf2).await;
+                        let content_res = ts_for_async.get_file_content(&sf2, &filter).await;
+                        let long_lines = content_res
+                            .as_ref()
+                            .is_ok_and(|content| content.lines().any(|l| l.len() > LONG_LINE_BYTES));
                         let final_res = match content_res {
                             Ok(content) => {
-                                match count_tokens_in_content(&content) {
+                                match count_tokens_in_content(&content, model) {
                                     Ok(n) => Ok(n),
-                                    Err(e) => Err(e),
+                                    Err(e) => Err(TextSourceError::TokenCountFailed(e)),
                                 }
                             }
-                            Err(e) => Err(e.to_string()),
+                            Err(e) => Err(e),
                         };
-                        if let Err(e) = tx.send((p, final_res)) {
+                        let message = TokenCountResult {
+                            path: p,
+                            revision: sf2.revision,
+                            model,
+                            result: final_res,
+                            long_lines,
+                        };
+                        if let Err(e) = tx.send(message) {
                             log::error!("Error sending token count result: {}", e);
                         }
                     });
@@ -447,9 +1943,222 @@ impl App {
     }
 
     fn process_token_count_results(&mut self) {
-        while let Ok((path, result)) = self.token_count_rx.try_recv() {
-            self.source_files_panel.set_count_result(&path, result);
+        let mut received = false;
+        while let Ok(message) = self.token_count_rx.try_recv() {
+            let TokenCountResult { path, revision, model, result, long_lines } = message;
+            if long_lines {
+                self.source_files_panel.long_line_files.insert(path.clone());
+            } else if result.is_ok() {
+                self.source_files_panel.long_line_files.remove(&path);
+            }
+            if let (Ok(n), Some(revision)) = (&result, revision) {
+                self.token_cache.insert((path.clone(), revision, model), *n);
+            }
+            if model != self.tokenizer {
+                continue;
+            }
+            if let Err(e) = &result {
+                self.offer_text_override(e, false);
+            }
+            self.source_files_panel.set_count_result(&path, result.map_err(|e| e.to_string()));
+            self.count_finished += 1;
+            received = true;
+        }
+        if received {
+            self.refresh_extension_weights();
+            self.deselect_small_files();
+            if self.source_files_panel.is_counting() {
+                self.source_files_panel.update_title_counting(&self.selected_files);
+            } else if let Some(started) = self.count_started.take() {
+                let elapsed = started.elapsed();
+                log::info!(
+                    "Counted tokens for {} files in {:.1}s ({:.0} files/s)",
+                    self.count_finished,
+                    elapsed.as_secs_f64(),
+                    self.count_finished as f64 / elapsed.as_secs_f64().max(0.001)
+                );
+                if let Some(timer) = self.count_timer.take() {
+                    self.debug_panel.count = Some(timer.finish(self.count_finished));
+                }
+                self.count_finished = 0;
+            }
         }
         self.source_files_panel.update_title_sum(&self.selected_files);
     }
-}
\ No newline at end of file
+
+    fn toggle_pin_under_cursor(&mut self) {
+        let Some(path) = self.source_files_panel.items.get(self.source_files_panel.cursor).cloned() else {
+            return;
+        };
+        if !self.pinned_files.remove(&path) {
+            self.pinned_files.insert(path);
+        }
+    }
+
+    fn apply_pinned(&mut self) {
+        let mut added = false;
+        for path in &self.source_files_panel.items {
+            if self.pinned_files.contains(path) && self.selected_files.insert(path.clone()) {
+                added = true;
+            }
+        }
+        if added {
+            self.sync_extension_selection();
+            self.source_files_panel.refresh_title(&self.selected_files);
+        }
+    }
+
+    fn deselect_small_files(&mut self) {
+        if self.source_files_panel.min_tokens == 0 {
+            return;
+        }
+        for path in self.source_files_panel.files_below_min_tokens() {
+            if self.pinned_files.contains(&path) {
+                continue;
+            }
+            if self.selected_files.remove(&path) {
+                self.selected_extensions.remove(&extension_group(&path));
+                self.selected_extensions.remove("*");
+            }
+        }
+    }
+}
+
+fn draw_too_small(f: &mut Frame, area: Rect) {
+    let lines = vec![
+        Line::from("Terminal too small"),
+        Line::from(format!("{}x{} (need {}x{})", area.width, area.height, MIN_WIDTH, MIN_HEIGHT)),
+    ];
+    let height = (lines.len() as u16).min(area.height);
+    let top = Rect {
+        y: area.y + (area.height - height) / 2,
+        height,
+        ..area
+    };
+    let paragraph = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(paragraph, top);
+}
+
+fn preview_area(area: Rect) -> Rect {
+    Rect {
+        x: area.x + 2,
+        y: area.y + 1,
+        width: area.width.saturating_sub(4),
+        height: area.height.saturating_sub(3),
+    }
+}
+
+fn merge_summary(stats: &MergeStats) -> String {
+    let mut summary = format!(
+        "Merged {} files  •  {} bytes  •  {} lines  •  {} words",
+        format_number(stats.files),
+        format_number(stats.bytes),
+        format_number(stats.lines),
+        format_number(stats.words)
+    );
+    let mut by_extension: Vec<_> = stats.by_extension.iter().collect();
+    by_extension.sort_by(|a, b| b.1.tokens.cmp(&a.1.tokens).then(a.0.cmp(b.0)));
+    let languages: Vec<String> = by_extension
+        .iter()
+        .take(5)
+        .map(|(ext, s)| {
+            format!(
+                "{}: {} files, {} tokens",
+                ext,
+                format_number(s.files),
+                format_number(s.tokens)
+            )
+        })
+        .collect();
+    if !languages.is_empty() {
+        summary.push_str("  •  ");
+        summary.push_str(&languages.join("; "));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_with_files(count: usize) -> App {
+        let mut app = App::new(".".to_string());
+        app.loaded_files = (0..count)
+            .map(|i| SourceFile {
+                path: format!("dir{}/file{:03}.{}", i % 7, i, ["rs", "md", "toml", "txt"][i % 4]),
+                source_type: SourceType::FileSystem {
+                    base_path: PathBuf::from("."),
+                    relative_path: PathBuf::from(format!("file{:03}", i)),
+                },
+                revision: None,
+            })
+            .collect();
+        app.filters_panel.init_values(&app.loaded_files, &mut app.selected_extensions, &mut app.selected_files);
+        app.source_files_panel.init_values(&app.loaded_files, &mut app.selected_files);
+        app
+    }
+
+    fn assert_in_bounds(app: &App, width: u16, height: u16) {
+        let area = Rect::new(0, 0, width, height);
+        let (main_chunks, mid) = app.panel_areas(area);
+        for rect in main_chunks.iter().chain(mid.iter()) {
+            assert!(rect.right() <= area.right() && rect.bottom() <= area.bottom(), "{:?} outside {:?}", rect, area);
+        }
+        let filters = &app.filters_panel;
+        assert!(filters.visible_height <= mid[0].height as usize);
+        assert!(filters.cursor < filters.items.len());
+        assert!(filters.offset <= filters.cursor);
+        if filters.visible_height > 0 {
+            assert!(filters.cursor < filters.offset + filters.visible_height);
+        }
+        let files = &app.source_files_panel;
+        assert!(files.visible_height <= mid[1].height as usize);
+        assert!(files.cursor < files.items.len());
+        assert!(files.offset <= files.cursor);
+        if files.visible_height > 0 {
+            assert!(files.cursor < files.offset + files.visible_height);
+        }
+    }
+
+    #[test]
+    fn resize_keeps_panels_and_cursors_in_bounds() {
+        let mut app = app_with_files(200);
+        app.filters_panel.cursor = app.filters_panel.items.len() - 1;
+        app.source_files_panel.cursor = 150;
+        for (width, height) in [(200, 60), (40, 12), (10, 3), (300, 120), (80, 15)] {
+            app.resize(width, height);
+            assert_in_bounds(&app, width, height);
+        }
+    }
+
+    #[test]
+    fn growing_the_terminal_keeps_the_cursor_visible() {
+        let mut app = app_with_files(50);
+        app.source_files_panel.cursor = 49;
+        app.resize(80, 14);
+        let small_offset = app.source_files_panel.offset;
+        assert!(small_offset > 0);
+        app.resize(80, 100);
+        assert_in_bounds(&app, 80, 100);
+        assert!(app.source_files_panel.offset <= small_offset);
+    }
+
+    #[tokio::test]
+    async fn incremental_reload_leaves_new_generated_files_unselected() {
+        let dir = std::env::temp_dir().join(format!("anvil-generated-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let mut app = App::new(dir.to_string_lossy().into_owned());
+        app.generated_patterns = vec!["package-lock.json".to_string()];
+        app.reload_files_immediate().await;
+        std::fs::write(dir.join("package-lock.json"), "{}").unwrap();
+        std::fs::write(dir.join("lib.rs"), "").unwrap();
+        app.reload_files_immediate().await;
+        assert!(app.selected_files.contains("lib.rs"));
+        assert!(!app.selected_files.contains("package-lock.json"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::output::tree::TreeStyle;
 use crossterm::event::{KeyCode, KeyEvent};
+use serde::{Deserialize, Serialize};
 use ratatui::{
     layout::Rect,
     style::{Color, Style},
@@ -6,17 +8,60 @@ use ratatui::{
     widgets::{Block, Borders, Tabs},
 };
 
-#[derive(Clone, PartialEq)]
+pub const DEFAULT_WRAP_COLUMN: usize = 200;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum OutputDestination {
     FileAndClipboard,
     File,
     Clipboard,
+    Directory,
+}
+
+impl OutputDestination {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputDestination::FileAndClipboard => "File + Clipboard",
+            OutputDestination::File => "File",
+            OutputDestination::Clipboard => "Clipboard",
+            OutputDestination::Directory => "Directory",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum OutputFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn default_extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Json => "json",
+        }
+    }
 }
 
 pub struct OutputPanel {
     pub items: Vec<OutputDestination>,
     pub selected: usize,
     pub destination: OutputDestination,
+    pub format: OutputFormat,
+    pub deduplicate: bool,
+    pub strip_notebooks: bool,
+    pub file_summaries: bool,
+    pub tree_header: bool,
+    pub tree_style: TreeStyle,
+    pub table_of_contents: bool,
+    pub prefix_paths: bool,
+    pub redact_secrets: bool,
+    pub all_or_nothing: bool,
+    pub wrap_lines: bool,
+    pub wrap_column: usize,
 }
 
 impl OutputPanel {
@@ -25,33 +70,79 @@ impl OutputPanel {
             OutputDestination::FileAndClipboard,
             OutputDestination::File,
             OutputDestination::Clipboard,
+            OutputDestination::Directory,
         ];
         Self {
             items,
             selected: 0,
             destination: OutputDestination::FileAndClipboard,
+            format: OutputFormat::Text,
+            deduplicate: false,
+            strip_notebooks: false,
+            file_summaries: false,
+            tree_header: false,
+            tree_style: TreeStyle::BoxDrawing,
+            table_of_contents: false,
+            prefix_paths: false,
+            redact_secrets: false,
+            all_or_nothing: false,
+            wrap_lines: false,
+            wrap_column: DEFAULT_WRAP_COLUMN,
         }
     }
+    pub fn describe(&self) -> String {
+        let format = match self.format {
+            OutputFormat::Text => "text",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Json => "json",
+        };
+        let mut flags = vec![format.to_string()];
+        if self.deduplicate {
+            flags.push("deduplicated".to_string());
+        }
+        if self.strip_notebooks {
+            flags.push("notebook sources only".to_string());
+        }
+        if self.file_summaries {
+            flags.push("file summaries".to_string());
+        }
+        if self.tree_header {
+            flags.push(format!("{} tree", self.tree_style.name()));
+        }
+        if self.table_of_contents {
+            flags.push("contents".to_string());
+        }
+        if self.prefix_paths {
+            flags.push("prefixed paths".to_string());
+        }
+        if self.redact_secrets {
+            flags.push("REDACTING SECRETS".to_string());
+        }
+        if self.all_or_nothing {
+            flags.push("all or nothing".to_string());
+        }
+        if self.wrap_lines {
+            flags.push(format!("wrapped at {}", self.wrap_column));
+        }
+        flags.join(", ")
+    }
     pub fn draw(&mut self, f: &mut ratatui::Frame, area: Rect, focused: bool) {
         let block_style = if focused {
             Style::default().fg(Color::LightBlue)
         } else {
             Style::default()
         };
+        let title = format!("Output ({})", self.describe());
         let block = Block::default()
             .borders(Borders::ALL)
-            .title("Output")
+            .title(title)
             .style(block_style);
         let lines: Vec<Line> = self
             .items
             .iter()
             .enumerate()
             .map(|(i, o)| {
-                let text = match o {
-                    OutputDestination::FileAndClipboard => "File + Clipboard",
-                    OutputDestination::File => "File",
-                    OutputDestination::Clipboard => "Clipboard",
-                };
+                let text = o.label();
                 let selected = i == self.selected;
                 let icon = if selected { "[x]" } else { "[ ]" };
                 let style = if focused {
@@ -78,6 +169,10 @@ impl OutputPanel {
             .highlight_style(Style::default().fg(Color::White));
         f.render_widget(tabs, area);
     }
+    pub fn cycle_destination(&mut self) {
+        self.selected = (self.selected + 1) % self.items.len();
+        self.destination = self.items[self.selected].clone();
+    }
     pub fn handle_input(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Left => {
@@ -90,8 +185,39 @@ impl OutputPanel {
                     self.selected += 1;
                 }
             }
-            KeyCode::Enter => {
-                self.destination = self.items[self.selected].clone();
+            KeyCode::Char('f') => {
+                self.format = match self.format {
+                    OutputFormat::Text => OutputFormat::Markdown,
+                    OutputFormat::Markdown => OutputFormat::Json,
+                    OutputFormat::Json => OutputFormat::Text,
+                };
+            }
+            KeyCode::Char('d') => {
+                self.deduplicate = !self.deduplicate;
+            }
+            KeyCode::Char('n') => {
+                self.strip_notebooks = !self.strip_notebooks;
+            }
+            KeyCode::Char('s') => {
+                self.file_summaries = !self.file_summaries;
+            }
+            KeyCode::Char('h') => {
+                self.tree_header = !self.tree_header;
+            }
+            KeyCode::Char('c') => {
+                self.table_of_contents = !self.table_of_contents;
+            }
+            KeyCode::Char('p') => {
+                self.prefix_paths = !self.prefix_paths;
+            }
+            KeyCode::Char('r') => {
+                self.redact_secrets = !self.redact_secrets;
+            }
+            KeyCode::Char('a') => {
+                self.all_or_nothing = !self.all_or_nothing;
+            }
+            KeyCode::Char('w') => {
+                self.wrap_lines = !self.wrap_lines;
             }
             _ => {}
         }
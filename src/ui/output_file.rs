@@ -21,14 +21,14 @@ impl OutputFilePanel {
         }
     }
 
-    pub fn draw(&self, f: &mut ratatui::Frame, area: Rect, focused: bool) {
+    pub fn draw(&self, f: &mut ratatui::Frame, area: Rect, focused: bool, directory: bool) {
         let block_style = if focused {
             Style::default().fg(Color::LightBlue)
         } else {
             Style::default()
         };
         let block = Block::default()
-            .title("Output file")
+            .title(if directory { "Output directory" } else { "Output file" })
             .borders(Borders::ALL)
             .style(block_style);
         let mut spans = Vec::new();
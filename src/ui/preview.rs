@@ -0,0 +1,133 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use once_cell::sync::Lazy;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use crate::input::file_system::get_extension;
+
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+pub struct PreviewPanel {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub scroll: usize,
+    pub page_size: usize,
+    highlighter: Option<HighlightLines<'static>>,
+    highlighted: Vec<Vec<(Style, String)>>,
+}
+
+impl PreviewPanel {
+    pub fn new(path: String, content: &str) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let highlighter = if no_color {
+            None
+        } else {
+            get_extension(&path)
+                .and_then(|ext| SYNTAX_SET.find_syntax_by_extension(&ext))
+                .map(|syntax| HighlightLines::new(syntax, &THEME_SET.themes["base16-ocean.dark"]))
+        };
+        Self {
+            path,
+            lines: content.lines().map(|l| l.replace('\t', "    ")).collect(),
+            scroll: 0,
+            page_size: 10,
+            highlighter,
+            highlighted: Vec::new(),
+        }
+    }
+
+    fn highlight_until(&mut self, end: usize) {
+        let Some(highlighter) = self.highlighter.as_mut() else {
+            return;
+        };
+        while self.highlighted.len() < end {
+            let line = format!("{}\n", self.lines[self.highlighted.len()]);
+            let spans = match highlighter.highlight_line(&line, &SYNTAX_SET) {
+                Ok(ranges) => ranges
+                    .into_iter()
+                    .map(|(style, text)| {
+                        let fg = style.foreground;
+                        (
+                            Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b)),
+                            text.trim_end_matches('\n').to_string(),
+                        )
+                    })
+                    .collect(),
+                Err(_) => {
+                    self.highlighter = None;
+                    return;
+                }
+            };
+            self.highlighted.push(spans);
+        }
+    }
+
+    pub fn draw(&mut self, f: &mut ratatui::Frame, area: Rect) {
+        let visible_count = area.height.saturating_sub(2) as usize;
+        self.resize(visible_count);
+        let end = (self.scroll + visible_count).min(self.lines.len());
+        let title = format!(
+            "Preview: {} (lines {}-{} of {})",
+            self.path,
+            (self.scroll + 1).min(end),
+            end,
+            self.lines.len()
+        );
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::LightBlue));
+        self.highlight_until(end);
+        let lines: Vec<Line> = (self.scroll..end)
+            .map(|i| match self.highlighted.get(i) {
+                Some(spans) if self.highlighter.is_some() => Line::from(
+                    spans
+                        .iter()
+                        .map(|(style, text)| Span::styled(text.as_str(), *style))
+                        .collect::<Vec<_>>(),
+                ),
+                _ => Line::styled(self.lines[i].as_str(), Style::default().fg(Color::White)),
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines).block(block);
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+
+    pub fn resize(&mut self, visible_count: usize) {
+        self.page_size = visible_count.max(1);
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(self.page_size));
+    }
+
+    pub fn handle_input(&mut self, key: KeyEvent) {
+        let max_scroll = self.lines.len().saturating_sub(self.page_size);
+        match key.code {
+            KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.scroll = (self.scroll + 1).min(max_scroll);
+            }
+            KeyCode::PageUp => {
+                self.scroll = self.scroll.saturating_sub(self.page_size);
+            }
+            KeyCode::PageDown => {
+                self.scroll = (self.scroll + self.page_size).min(max_scroll);
+            }
+            KeyCode::Home => {
+                self.scroll = 0;
+            }
+            KeyCode::End => {
+                self.scroll = max_scroll;
+            }
+            _ => {}
+        }
+    }
+}
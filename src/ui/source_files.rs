@@ -2,10 +2,13 @@ use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     layout::Rect,
     style::{Color, Style},
+    text::{Line, Span},
     widgets::{Block, Borders, ListItem},
 };
 use std::collections::{HashMap, HashSet};
-use crate::input::SourceFile;
+use crate::input::{file_system::get_extension, SourceFile};
+use crate::output::pricing::{format_cost, ModelPrice};
+use crate::ui::filters::extension_group;
 
 pub enum TokenStatus {
     NotCounted,
@@ -20,8 +23,22 @@ pub struct SourceFilesPanel {
     pub offset: usize,
     pub file_token_status: HashMap<String, TokenStatus>,
     pub panel_title: String,
+    pub visible_height: usize,
+    pub min_tokens: usize,
+    pub extra_tokens: usize,
+    pub search_input: Option<String>,
+    pub pattern_input: Option<(bool, String)>,
+    pub search_matches: HashSet<String>,
+    pub added_files: HashSet<String>,
+    pub long_line_files: HashSet<String>,
+    pub color_by_type: bool,
+    pub price: Option<ModelPrice>,
 }
 
+pub const LONG_LINE_BYTES: usize = 1000;
+
+const MIN_TOKEN_STEPS: [usize; 8] = [0, 10, 25, 50, 100, 250, 500, 1000];
+
 impl SourceFilesPanel {
     pub fn new() -> Self {
         Self {
@@ -30,6 +47,16 @@ impl SourceFilesPanel {
             offset: 0,
             file_token_status: HashMap::new(),
             panel_title: "Files".to_string(),
+            visible_height: 10,
+            min_tokens: 0,
+            extra_tokens: 0,
+            search_input: None,
+            pattern_input: None,
+            search_matches: HashSet::new(),
+            added_files: HashSet::new(),
+            long_line_files: HashSet::new(),
+            color_by_type: std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+            price: None,
         }
     }
 
@@ -44,16 +71,81 @@ impl SourceFilesPanel {
         self.cursor = 0;
         self.offset = 0;
         self.panel_title = "Files".to_string();
+        self.search_matches.clear();
+        self.added_files.clear();
+        self.long_line_files.clear();
+    }
+
+    pub fn refresh_values(
+        &mut self,
+        files: &[SourceFile],
+        previous: &[SourceFile],
+        selected_files: &mut HashSet<String>,
+    ) {
+        let previous_by_path: HashMap<&str, &SourceFile> =
+            previous.iter().map(|f| (f.path.as_str(), f)).collect();
+        let mut statuses = HashMap::new();
+        for f in files {
+            match previous_by_path.get(f.path.as_str()) {
+                Some(old) if old.revision.is_some() && old.revision == f.revision => {
+                    if let Some(status) = self.file_token_status.remove(&f.path) {
+                        statuses.insert(f.path.clone(), status);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    selected_files.insert(f.path.clone());
+                }
+            }
+            statuses.entry(f.path.clone()).or_insert(TokenStatus::NotCounted);
+        }
+        selected_files.retain(|p| statuses.contains_key(p));
+        self.file_token_status = statuses;
+        let mut paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+        paths.sort();
+        self.items = paths;
+        self.ensure_cursor_visible(self.visible_height);
+        self.panel_title = "Files".to_string();
     }
 
-    pub fn draw(&self, f: &mut ratatui::Frame, area: Rect, focused: bool, selected_files: &HashSet<String>) {
+    pub fn draw(
+        &mut self,
+        f: &mut ratatui::Frame,
+        area: Rect,
+        focused: bool,
+        selected_files: &HashSet<String>,
+        pinned_files: &HashSet<String>,
+        merge_positions: &HashMap<String, usize>,
+    ) {
+        self.visible_height = area.height.saturating_sub(2) as usize;
+        self.ensure_cursor_visible(self.visible_height);
         let block_style = if focused {
             Style::default().fg(Color::LightBlue)
         } else {
             Style::default()
         };
+        let mut title = format!(
+            "{} ({}/{} selected)",
+            self.panel_title,
+            format_number(selected_files.len()),
+            format_number(self.items.len())
+        );
+        if self.min_tokens > 0 {
+            title = format!("{} (min {} tokens)", title, format_number(self.min_tokens));
+        }
+        if !merge_positions.is_empty() {
+            title = format!("{} (custom order)", title);
+        }
+        if let Some(query) = &self.search_input {
+            title = format!("{} - search: {}█", title, query);
+        } else if let Some((select, pattern)) = &self.pattern_input {
+            let action = if *select { "select" } else { "deselect" };
+            title = format!("{} - {} glob: {}█", title, action, pattern);
+        } else if !self.search_matches.is_empty() {
+            title = format!("{} ({} matches)", title, self.search_matches.len());
+        }
         let block = Block::default()
-            .title(self.panel_title.as_str())
+            .title(title)
             .borders(Borders::ALL)
             .style(block_style);
 
@@ -76,10 +168,19 @@ impl SourceFilesPanel {
             .map(|(idx_in_slice, it)| {
                 let i = self.offset + idx_in_slice;
                 let is_selected = selected_files.contains(it);
-                let icon = if is_selected { "[x]" } else { "[ ]" };
+                let icon = if pinned_files.contains(it) {
+                    "[#]"
+                } else if is_selected {
+                    "[x]"
+                } else {
+                    "[ ]"
+                };
                 let prefix = if i == self.cursor { "> " } else { "  " };
+                let is_match = self.search_matches.contains(it);
                 let item_style = if focused && i == self.cursor {
                     Style::default().fg(Color::LightBlue)
+                } else if is_match {
+                    Style::default().fg(Color::Yellow)
                 } else if is_selected {
                     Style::default().fg(Color::White)
                 } else {
@@ -90,8 +191,29 @@ impl SourceFilesPanel {
                 let status_str = status_map[it].clone();
                 let right_aligned_status = format!("{:>width$}", status_str, width = max_status_len);
 
-                let line = format!("{}{} {}  {}", prefix, icon, padded_path, right_aligned_status);
-                ListItem::new(line).style(item_style)
+                let marker = if is_match {
+                    "*"
+                } else if self.added_files.contains(it) {
+                    "+"
+                } else {
+                    " "
+                };
+                let path_style = match type_color(it) {
+                    Some(color) if self.color_by_type && is_selected && !is_match && !(focused && i == self.cursor) => {
+                        Style::default().fg(color)
+                    }
+                    _ => item_style,
+                };
+                let mut spans = vec![
+                    Span::styled(format!("{}{}{}", prefix, icon, marker), item_style),
+                    Span::styled(padded_path, path_style),
+                    Span::styled(format!("  {}", right_aligned_status), item_style),
+                ];
+                if let Some(position) = merge_positions.get(it) {
+                    spans.push(Span::styled(format!("  #{}", position), item_style));
+                }
+                let line = Line::from(spans);
+                ListItem::new(line)
             })
             .collect();
 
@@ -104,22 +226,27 @@ impl SourceFilesPanel {
             KeyCode::Up => {
                 if self.cursor > 0 {
                     self.cursor -= 1;
-                    if self.cursor < self.offset {
-                        self.offset = self.cursor;
-                    }
                 }
             }
             KeyCode::Down => {
                 if self.cursor + 1 < self.items.len() {
                     self.cursor += 1;
-                    let visible_count = 10;
-                    if self.cursor >= self.offset + visible_count {
-                        self.offset = self.cursor + 1 - visible_count;
-                    }
                 }
             }
             _ => {}
         }
+        self.ensure_cursor_visible(self.visible_height);
+    }
+
+    pub fn ensure_cursor_visible(&mut self, visible_height: usize) {
+        if self.cursor >= self.items.len() {
+            self.cursor = self.items.len().saturating_sub(1);
+        }
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if visible_height > 0 && self.cursor >= self.offset + visible_height {
+            self.offset = self.cursor + 1 - visible_height;
+        }
     }
 
     pub fn toggle_selected(
@@ -136,29 +263,19 @@ impl SourceFilesPanel {
             selected_files.remove(current_file);
             for f in all_files {
                 if f.path == *current_file {
-                    if let Some(ext) = current_file.split('.').last() {
-                        if selected_exts.contains(ext) {
-                            selected_exts.remove(ext);
-                        }
-                    }
+                    selected_exts.remove(&extension_group(current_file));
                 }
             }
             selected_exts.remove("*");
         } else {
             selected_files.insert(current_file.clone());
-            if let Some(ext) = current_file.split('.').last() {
-                let mut all_same_ext = true;
-                for f in all_files {
-                    if let Some(e2) = f.path.split('.').last() {
-                        if e2 == ext && !selected_files.contains(&f.path) {
-                            all_same_ext = false;
-                            break;
-                        }
-                    }
-                }
-                if all_same_ext {
-                    selected_exts.insert(ext.to_string());
-                }
+            let ext = extension_group(current_file);
+            let all_same_ext = all_files
+                .iter()
+                .filter(|f| extension_group(&f.path) == ext)
+                .all(|f| selected_files.contains(&f.path));
+            if all_same_ext {
+                selected_exts.insert(ext);
             }
         }
     }
@@ -178,14 +295,75 @@ impl SourceFilesPanel {
         }
     }
 
-    pub fn update_title_counting(&mut self) {
-        self.panel_title = "Files (counting tokens)".to_string();
+    pub fn increase_min_tokens(&mut self) {
+        if let Some(next) = MIN_TOKEN_STEPS.iter().find(|&&n| n > self.min_tokens) {
+            self.min_tokens = *next;
+        }
+    }
+
+    pub fn decrease_min_tokens(&mut self) {
+        self.min_tokens = MIN_TOKEN_STEPS
+            .iter()
+            .rev()
+            .find(|&&n| n < self.min_tokens)
+            .copied()
+            .unwrap_or(0);
+    }
+
+    pub fn files_below_min_tokens(&self) -> Vec<String> {
+        self.file_token_status
+            .iter()
+            .filter_map(|(path, status)| match status {
+                TokenStatus::Done(n) if *n < self.min_tokens => Some(path.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn update_title_counting(&mut self, selected_files: &HashSet<String>) {
+        let counted = selected_files
+            .iter()
+            .filter(|p| matches!(self.file_token_status.get(*p), Some(TokenStatus::Done(_)) | Some(TokenStatus::Error)))
+            .count();
+        self.panel_title = format!(
+            "Files (counting tokens {}/{})",
+            format_number(counted),
+            format_number(selected_files.len())
+        );
+    }
+
+    pub fn is_counting(&self) -> bool {
+        self.file_token_status
+            .values()
+            .any(|s| matches!(s, TokenStatus::Counting))
     }
 
     pub fn update_title_sum(&mut self, selected_files: &HashSet<String>) {
         if let Some(sum) = self.maybe_compute_total_tokens(selected_files) {
-            self.panel_title = format!("Files ({} tokens)", format_number(sum));
+            self.panel_title = match &self.price {
+                Some(price) => format!(
+                    "Files ({} tokens ≈ {} on {})",
+                    format_number(sum),
+                    format_cost(price.cost(sum)),
+                    price.name
+                ),
+                None => format!("Files ({} tokens)", format_number(sum)),
+            };
+        }
+    }
+
+    pub fn known_token_count(&self, path: &str) -> Option<usize> {
+        match self.file_token_status.get(path) {
+            Some(TokenStatus::Done(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn refresh_title(&mut self, selected_files: &HashSet<String>) {
+        if !self.is_counting() {
+            self.panel_title = "Files".to_string();
         }
+        self.update_title_sum(selected_files);
     }
 
     fn maybe_compute_total_tokens(&self, selected_files: &HashSet<String>) -> Option<usize> {
@@ -195,7 +373,7 @@ impl SourceFilesPanel {
                 _ => {}
             }
         }
-        let mut total = 0;
+        let mut total = self.extra_tokens;
         for path in selected_files {
             if let Some(TokenStatus::Done(n)) = self.file_token_status.get(path) {
                 total += n;
@@ -207,6 +385,9 @@ impl SourceFilesPanel {
     fn get_status_string(&self, path: &str) -> String {
         match self.file_token_status.get(path) {
             Some(TokenStatus::Counting) => "...".to_owned(),
+            Some(TokenStatus::Done(n)) if self.long_line_files.contains(path) => {
+                format!("long lines  {}", format_token_count(*n))
+            }
             Some(TokenStatus::Done(n)) => format_token_count(*n),
             Some(TokenStatus::Error) => "Error".to_owned(),
             _ => "".to_owned(),
@@ -214,12 +395,25 @@ impl SourceFilesPanel {
     }
 }
 
+fn type_color(path: &str) -> Option<Color> {
+    let ext = get_extension(path)?;
+    match ext.as_str() {
+        "rs" | "py" | "js" | "jsx" | "ts" | "tsx" | "go" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs"
+        | "java" | "kt" | "swift" | "rb" | "php" | "scala" | "lua" | "dart" | "ex" | "exs" | "hs"
+        | "sh" | "bash" | "ps1" | "sql" | "vue" | "svelte" | "html" | "css" | "scss" => Some(Color::Cyan),
+        "md" | "markdown" | "txt" | "rst" | "adoc" | "org" | "tex" => Some(Color::Green),
+        "toml" | "yaml" | "yml" | "json" | "ini" | "cfg" | "conf" | "xml" | "lock" | "env"
+        | "properties" | "gradle" | "csproj" => Some(Color::Magenta),
+        _ => None,
+    }
+}
+
 fn format_token_count(n: usize) -> String {
     let s = format_number(n);
     format!("{} tokens", s)
 }
 
-fn format_number(n: usize) -> String {
+pub fn format_number(n: usize) -> String {
     let s = n.to_string();
     let mut result = String::new();
     let mut count = 0;
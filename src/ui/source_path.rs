@@ -21,14 +21,19 @@ impl SourcePathPanel {
         }
     }
 
-    pub fn draw(&self, f: &mut ratatui::Frame, area: Rect, focused: bool) {
+    pub fn draw(&self, f: &mut ratatui::Frame, area: Rect, focused: bool, watching: bool) {
         let block_style = if focused {
             Style::default().fg(Color::LightBlue)
         } else {
             Style::default()
         };
+        let title = if watching {
+            "Source (Directory path or Github URL) - watching"
+        } else {
+            "Source (Directory path or Github URL)"
+        };
         let block = Block::default()
-            .title("Source (Directory path or Github URL)")
+            .title(title)
             .borders(Borders::ALL)
             .style(block_style);
         let mut spans = Vec::new();
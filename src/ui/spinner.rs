@@ -0,0 +1,50 @@
+use crossterm::{
+    cursor::{MoveTo, RestorePosition, SavePosition},
+    execute,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+pub const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+pub struct Spinner {
+    done: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Spinner {
+    pub fn start(position: (u16, u16)) -> Self {
+        let done = Arc::new(AtomicBool::new(false));
+        let stop = done.clone();
+        let handle = std::thread::spawn(move || {
+            let mut frame = 1;
+            loop {
+                std::thread::park_timeout(Duration::from_millis(100));
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let _ = execute!(
+                    std::io::stdout(),
+                    SavePosition,
+                    MoveTo(position.0, position.1),
+                    SetForegroundColor(Color::White),
+                    SetBackgroundColor(Color::Black),
+                    Print(SPINNER_FRAMES[frame % SPINNER_FRAMES.len()]),
+                    ResetColor,
+                    RestorePosition
+                );
+                frame += 1;
+            }
+        });
+        Self { done, handle }
+    }
+
+    pub fn stop(self) {
+        self.done.store(true, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        let _ = self.handle.join();
+    }
+}